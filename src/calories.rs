@@ -1,7 +1,12 @@
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::io::{self, BufRead};
+
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
 struct Pack {
-    food: Vec<i32>,
-    total: i32,
+    food: Vec<u64>,
+    total: u64,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -9,47 +14,369 @@ pub struct Calories {
     packs: Vec<Pack>,
 }
 
-impl Calories {
-    pub fn from_str(input: &str) -> Calories {
-        let mut packs: Vec<Pack> = vec![];
-        let mut pack = Pack {
-            food: vec![],
-            total: 0,
-        };
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseMode {
+    Strict,
+    Lenient,
+}
 
-        for line in input.split('\n') {
-            if let Ok(cals) = line.parse::<i32>() {
-                pack.food.push(cals);
-                pack.total += cals;
-            } else {
-                packs.push(pack);
-                pack = Pack {
-                    food: vec![],
-                    total: 0,
-                };
+impl ParseMode {
+    fn clean<'a>(&self, line: &'a str) -> &'a str {
+        match self {
+            ParseMode::Strict => line,
+            ParseMode::Lenient => line.trim(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    InvalidLine { line: usize, content: String },
+    Overflow { line: usize },
+    Io(io::ErrorKind),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CalorieError {
+    EmptyInventory,
+    Overflow,
+}
+
+impl fmt::Display for CalorieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalorieError::EmptyInventory => write!(f, "no elves in the inventory"),
+            CalorieError::Overflow => write!(f, "calorie total overflows a 64-bit count"),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::InvalidLine { line, content } => {
+                write!(
+                    f,
+                    "line {}: expected a calorie count, found {:?}",
+                    line, content
+                )
+            }
+            ParseError::Overflow { line } => {
+                write!(f, "line {}: pack total overflows a 64-bit count", line)
             }
+            ParseError::Io(kind) => write!(f, "failed to read input: {}", kind),
         }
-        if !pack.food.is_empty() {
-            packs.push(pack);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Retain {
+    Food,
+    TotalsOnly,
+}
+
+struct PackFolder<F: FnMut(usize, Pack)> {
+    mode: ParseMode,
+    retain: Retain,
+    on_pack: F,
+    packs: usize,
+    pack: Pack,
+    in_pack: bool,
+}
+
+impl<F: FnMut(usize, Pack)> PackFolder<F> {
+    fn new(mode: ParseMode, retain: Retain, on_pack: F) -> PackFolder<F> {
+        PackFolder {
+            mode,
+            retain,
+            on_pack,
+            packs: 0,
+            pack: Pack::default(),
+            in_pack: false,
+        }
+    }
+
+    fn close_pack(&mut self) {
+        (self.on_pack)(self.packs, std::mem::take(&mut self.pack));
+        self.packs += 1;
+        self.in_pack = false;
+    }
+
+    fn fold_line(&mut self, line_number: usize, line: &str) -> Result<(), ParseError> {
+        let line = self.mode.clean(line);
+        if line.is_empty() {
+            if self.in_pack {
+                self.close_pack();
+            }
+        } else if let Ok(cals) = line.parse::<u64>() {
+            if self.retain == Retain::Food {
+                self.pack.food.push(cals);
+            }
+            self.pack.total = self
+                .pack
+                .total
+                .checked_add(cals)
+                .ok_or(ParseError::Overflow { line: line_number })?;
+            self.in_pack = true;
+        } else {
+            return Err(ParseError::InvalidLine {
+                line: line_number,
+                content: String::from(line),
+            });
         }
+        Ok(())
+    }
 
-        Calories { packs }
+    fn finish(mut self) -> usize {
+        if self.in_pack {
+            self.close_pack();
+        }
+        self.packs
     }
+}
 
-    fn sort(&mut self) {
-        self.packs.sort_by_key(|t| t.total);
-        self.packs.reverse();
+fn fold_lines<R: BufRead, F: FnMut(usize, Pack)>(
+    mut reader: R,
+    mode: ParseMode,
+    retain: Retain,
+    on_pack: F,
+) -> Result<usize, ParseError> {
+    let mut folder = PackFolder::new(mode, retain, on_pack);
+    let mut buffer = String::new();
+    let mut line_number = 0;
+    loop {
+        buffer.clear();
+        let read = reader
+            .read_line(&mut buffer)
+            .map_err(|err| ParseError::Io(err.kind()))?;
+        if read == 0 {
+            break;
+        }
+        line_number += 1;
+        folder.fold_line(line_number, buffer.strip_suffix('\n').unwrap_or(&buffer))?;
     }
+    Ok(folder.finish())
+}
 
-    pub fn max(&mut self) -> i32 {
-        self.sort();
-        self.packs.first().unwrap().total
+// Keeps the n largest totals seen so far, ties going to the earlier elf
+struct TopN {
+    n: usize,
+    heap: BinaryHeap<Reverse<(u64, Reverse<usize>)>>,
+}
+
+impl TopN {
+    fn new(n: usize) -> TopN {
+        TopN {
+            n,
+            heap: BinaryHeap::new(),
+        }
+    }
+
+    fn push(&mut self, idx: usize, total: u64) {
+        self.heap.push(Reverse((total, Reverse(idx))));
+        if self.heap.len() > self.n {
+            self.heap.pop();
+        }
     }
 
-    pub fn total_n(&mut self, n: i32) -> i32 {
-        self.sort();
-        let top = self.packs.iter().take(n.try_into().unwrap());
-        top.fold(0, |acc, pack| acc + pack.total)
+    fn into_vec(self) -> Vec<(usize, u64)> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((total, Reverse(idx)))| (idx, total))
+            .collect()
+    }
+}
+
+impl Calories {
+    pub fn from_str(input: &str) -> Result<Calories, ParseError> {
+        Calories::parse(input, ParseMode::Lenient)
+    }
+
+    pub fn parse(input: &str, mode: ParseMode) -> Result<Calories, ParseError> {
+        let mut packs = vec![];
+        let mut folder = PackFolder::new(mode, Retain::Food, |_, pack| packs.push(pack));
+        for (idx, line) in input.split('\n').enumerate() {
+            folder.fold_line(idx + 1, line)?;
+        }
+        folder.finish();
+        Ok(Calories { packs })
+    }
+
+    // Still keeps one entry per elf; use `fold_reader` when even that is too much
+    pub fn from_reader<R: BufRead>(
+        reader: R,
+        mode: ParseMode,
+        retain: Retain,
+    ) -> Result<Calories, ParseError> {
+        let mut packs = vec![];
+        fold_lines(reader, mode, retain, |_, pack| packs.push(pack))?;
+        Ok(Calories { packs })
+    }
+
+    // Hands each elf's index and total to `on_pack` as its pack ends, holding only the
+    // current pack in memory, and returns the number of elves
+    pub fn fold_reader<R: BufRead, F: FnMut(usize, u64)>(
+        reader: R,
+        mode: ParseMode,
+        mut on_pack: F,
+    ) -> Result<usize, ParseError> {
+        fold_lines(reader, mode, Retain::TotalsOnly, |idx, pack| {
+            on_pack(idx, pack.total)
+        })
+    }
+
+    pub fn top_n_from_reader<R: BufRead>(
+        reader: R,
+        mode: ParseMode,
+        n: usize,
+    ) -> Result<Vec<(usize, u64)>, ParseError> {
+        let mut top = TopN::new(n);
+        Calories::fold_reader(reader, mode, |idx, total| top.push(idx, total))?;
+        Ok(top.into_vec())
+    }
+
+    pub fn top_n(&self, n: usize) -> Vec<(usize, u64)> {
+        let mut top = TopN::new(n.min(self.packs.len()));
+        for (idx, pack) in self.packs.iter().enumerate() {
+            top.push(idx, pack.total);
+        }
+        top.into_vec()
+    }
+
+    pub fn max(&self) -> Result<u64, CalorieError> {
+        self.top_n(1)
+            .first()
+            .map(|(_, total)| *total)
+            .ok_or(CalorieError::EmptyInventory)
+    }
+
+    pub fn total_n(&self, n: usize) -> Result<u64, CalorieError> {
+        if self.packs.is_empty() {
+            return Err(CalorieError::EmptyInventory);
+        }
+        self.top_n(n)
+            .iter()
+            .try_fold(0u64, |acc, (_, total)| acc.checked_add(*total))
+            .ok_or(CalorieError::Overflow)
+    }
+
+    pub fn percentile(&self, p: f64) -> Option<u64> {
+        let totals = self.sorted_totals();
+        percentile_of(&totals, p)
+    }
+
+    pub fn statistics(&self, buckets: usize) -> Option<Statistics> {
+        let totals = self.sorted_totals();
+        let (min, max) = (*totals.first()?, *totals.last()?);
+        let count = totals.len();
+        let food_retained = self.packs.iter().all(|p| !p.food.is_empty());
+
+        let mean = totals.iter().map(|t| *t as f64).sum::<f64>() / count as f64;
+        let median = if count.is_multiple_of(2) {
+            (totals[count / 2 - 1] as f64 + totals[count / 2] as f64) / 2.0
+        } else {
+            totals[count / 2] as f64
+        };
+        let percentiles = [25, 75, 90]
+            .iter()
+            .map(|p| (*p, percentile_of(&totals, *p as f64).unwrap()))
+            .collect();
+
+        let buckets = buckets.max(1) as u64;
+        let width = (max - min) / buckets + 1;
+        let histogram = (0..buckets)
+            .map(|b| {
+                let start = min.saturating_add(b.saturating_mul(width));
+                let end = start.saturating_add(width - 1);
+                Bucket {
+                    start,
+                    end,
+                    count: totals.iter().filter(|t| (start..=end).contains(*t)).count(),
+                }
+            })
+            .collect();
+
+        Some(Statistics {
+            elves: count,
+            mean,
+            median,
+            percentiles,
+            largest_item: food_retained
+                .then(|| self.packs.iter().flat_map(|p| p.food.iter()).copied().max())
+                .flatten(),
+            items_per_elf: food_retained.then(|| self.packs.iter().map(|p| p.food.len()).collect()),
+            histogram,
+        })
+    }
+
+    fn sorted_totals(&self) -> Vec<u64> {
+        let mut totals: Vec<u64> = self.packs.iter().map(|p| p.total).collect();
+        totals.sort_unstable();
+        totals
+    }
+}
+
+fn percentile_of(sorted: &[u64], p: f64) -> Option<u64> {
+    if sorted.is_empty() || !(0.0..=100.0).contains(&p) {
+        return None;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.max(1) - 1])
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Bucket {
+    pub start: u64,
+    pub end: u64,
+    pub count: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Statistics {
+    pub elves: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub percentiles: Vec<(u8, u64)>,
+    pub largest_item: Option<u64>,
+    pub items_per_elf: Option<Vec<usize>>,
+    pub histogram: Vec<Bucket>,
+}
+
+impl fmt::Display for Statistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<16} {:>12}", "Elves", self.elves)?;
+        writeln!(f, "{:<16} {:>12.2}", "Mean", self.mean)?;
+        writeln!(f, "{:<16} {:>12.2}", "Median", self.median)?;
+        for (p, value) in self.percentiles.iter() {
+            writeln!(f, "{:<16} {:>12}", format!("{}th percentile", p), value)?;
+        }
+        let largest_item = match self.largest_item {
+            Some(item) => item.to_string(),
+            None => String::from("n/a"),
+        };
+        let items_per_elf = match &self.items_per_elf {
+            Some(items) => format!(
+                "{}-{}",
+                items.iter().min().unwrap_or(&0),
+                items.iter().max().unwrap_or(&0)
+            ),
+            None => String::from("n/a"),
+        };
+        writeln!(f, "{:<16} {:>12}", "Largest item", largest_item)?;
+        writeln!(f, "{:<16} {:>12}", "Items per elf", items_per_elf)?;
+        writeln!(f, "Histogram")?;
+        for bucket in self.histogram.iter() {
+            let row = format!(
+                "{:>8} - {:>8} | {:<5} {}",
+                bucket.start,
+                bucket.end,
+                bucket.count,
+                "#".repeat(bucket.count)
+            );
+            writeln!(f, "{}", row.trim_end())?;
+        }
+        Ok(())
     }
 }
 
@@ -74,7 +401,7 @@ mod tests {
 		10000"
             .replace('\t', "");
 
-        let calories = super::Calories::from_str(&input);
+        let calories = super::Calories::from_str(&input).unwrap();
         let expected = super::Calories {
             packs: vec![
                 super::Pack {
@@ -120,11 +447,11 @@ mod tests {
 		10000"
             .replace('\t', "");
 
-        let mut calories = super::Calories::from_str(&input);
+        let calories = super::Calories::from_str(&input).unwrap();
         let result = calories.max();
         println!("{:#?}", calories);
 
-        assert_eq!(result, 24000);
+        assert_eq!(result, Ok(24000));
     }
 
     #[test]
@@ -144,9 +471,243 @@ mod tests {
 		
 		10000"
             .replace('\t', "");
-        let mut calories = super::Calories::from_str(&input);
+        let calories = super::Calories::from_str(&input).unwrap();
         let result = calories.total_n(3);
 
-        assert_eq!(result, 45000);
+        assert_eq!(result, Ok(45000));
+    }
+
+    #[test]
+    fn it_rejects_garbage_lines_with_their_line_number() {
+        let input = "1000\n2000\n\n10O0\n";
+
+        let result = super::Calories::parse(input, super::ParseMode::Strict);
+        assert_eq!(
+            Err(super::ParseError::InvalidLine {
+                line: 4,
+                content: String::from("10O0"),
+            }),
+            result
+        );
+        assert!(super::Calories::parse(input, super::ParseMode::Lenient).is_err());
+        assert_eq!(result, super::Calories::from_str(input));
+    }
+
+    #[test]
+    fn it_rejects_whitespace_in_strict_mode() {
+        let input = "1000\r\n2000\r\n";
+
+        let result = super::Calories::parse(input, super::ParseMode::Strict);
+        assert_eq!(
+            Err(super::ParseError::InvalidLine {
+                line: 1,
+                content: String::from("1000\r"),
+            }),
+            result
+        );
+    }
+
+    #[test]
+    fn it_trims_whitespace_and_crlf_in_lenient_mode() {
+        let input = "1000\r\n 2000 \r\n\r\n  \r\n\t\r\n3000\r\n   \r\n";
+
+        let result = super::Calories::parse(input, super::ParseMode::Lenient);
+        let expected = super::Calories {
+            packs: vec![
+                super::Pack {
+                    food: vec![1000, 2000],
+                    total: 3000,
+                },
+                super::Pack {
+                    food: vec![3000],
+                    total: 3000,
+                },
+            ],
+        };
+        assert_eq!(Ok(expected), result);
+    }
+
+    #[test]
+    fn it_gets_the_top_n_elves_with_their_indices() {
+        let input = "1000\n2000\n3000\n\n4000\n\n5000\n6000\n\n7000\n8000\n9000\n\n10000";
+        let calories = super::Calories::from_str(input).unwrap();
+
+        assert_eq!(vec![(3, 24000), (2, 11000), (4, 10000)], calories.top_n(3));
+        assert_eq!(5, calories.top_n(10).len());
+        assert!(calories.top_n(0).is_empty());
+    }
+
+    #[test]
+    fn it_clamps_top_n_to_the_number_of_elves() {
+        let calories = super::Calories::from_str("1\n\n2").unwrap();
+
+        assert_eq!(vec![(1, 2), (0, 1)], calories.top_n(1 << 40));
+        assert_eq!(2, calories.top_n(usize::MAX).len());
+    }
+
+    #[test]
+    fn it_breaks_ties_by_elf_order() {
+        let calories = super::Calories::from_str("5\n\n7\n\n5\n\n7").unwrap();
+        assert_eq!(vec![(1, 7), (3, 7), (0, 5)], calories.top_n(3));
+    }
+
+    #[test]
+    fn it_reports_statistics_for_the_inventory() {
+        let input = "1000\n2000\n3000\n\n4000\n\n5000\n6000\n\n7000\n8000\n9000\n\n10000";
+        let calories = super::Calories::from_str(input).unwrap();
+        let stats = calories.statistics(2).unwrap();
+
+        assert_eq!(5, stats.elves);
+        assert_eq!(11000.0, stats.mean);
+        assert_eq!(10000.0, stats.median);
+        assert_eq!(
+            vec![(25, 6000), (75, 11000), (90, 24000)],
+            stats.percentiles
+        );
+        assert_eq!(Some(10000), stats.largest_item);
+        assert_eq!(Some(vec![3, 1, 2, 3, 1]), stats.items_per_elf);
+        assert_eq!(
+            vec![
+                super::Bucket {
+                    start: 4000,
+                    end: 14000,
+                    count: 4,
+                },
+                super::Bucket {
+                    start: 14001,
+                    end: 24001,
+                    count: 1,
+                },
+            ],
+            stats.histogram
+        );
+        assert_eq!(Some(4000), calories.percentile(0.0));
+        assert_eq!(None, calories.percentile(101.0));
+    }
+
+    #[test]
+    fn it_has_no_statistics_for_an_empty_inventory() {
+        let calories = super::Calories::from_str("").unwrap();
+        assert_eq!(None, calories.statistics(4));
+    }
+
+    #[test]
+    fn it_reports_pack_overflow_with_the_line_number() {
+        let input = format!("1\n\n{}\n1\n", u64::MAX);
+
+        let result = super::Calories::parse(&input, super::ParseMode::Strict);
+        assert_eq!(Err(super::ParseError::Overflow { line: 4 }), result);
+    }
+
+    #[test]
+    fn it_reports_overflow_when_totalling_packs() {
+        let input = format!("{}\n\n{}", u64::MAX, u64::MAX);
+        let calories = super::Calories::from_str(&input).unwrap();
+
+        assert_eq!(Ok(u64::MAX), calories.total_n(1));
+        assert_eq!(Err(super::CalorieError::Overflow), calories.total_n(2));
+    }
+
+    #[test]
+    fn it_totals_every_elf_when_asked_for_more_than_there_are() {
+        let calories = super::Calories::from_str("1000\n\n2000\n3000").unwrap();
+
+        assert_eq!(Ok(6000), calories.total_n(usize::MAX));
+    }
+
+    #[test]
+    fn it_returns_an_error_for_an_empty_inventory() {
+        let calories = super::Calories::from_str("\n\n").unwrap();
+
+        assert_eq!(Err(super::CalorieError::EmptyInventory), calories.max());
+        assert_eq!(
+            Err(super::CalorieError::EmptyInventory),
+            calories.total_n(3)
+        );
+    }
+
+    #[test]
+    fn it_streams_packs_from_a_reader() {
+        let input = "1000\n2000\n3000\n\n4000\n\n5000\n6000\n\n7000\n8000\n9000\n\n10000\n";
+
+        let streamed = super::Calories::from_reader(
+            input.as_bytes(),
+            super::ParseMode::Strict,
+            super::Retain::Food,
+        );
+        assert_eq!(super::Calories::from_str(input), streamed);
+    }
+
+    #[test]
+    fn it_keeps_only_totals_when_asked() {
+        let input = "1000\r\n2000\r\n\r\n4000\r\n";
+
+        let calories = super::Calories::from_reader(
+            input.as_bytes(),
+            super::ParseMode::Lenient,
+            super::Retain::TotalsOnly,
+        )
+        .unwrap();
+        let expected = super::Calories {
+            packs: vec![
+                super::Pack {
+                    food: vec![],
+                    total: 3000,
+                },
+                super::Pack {
+                    food: vec![],
+                    total: 4000,
+                },
+            ],
+        };
+        assert_eq!(expected, calories);
+
+        let stats = calories.statistics(1).unwrap();
+        assert_eq!(None, stats.largest_item);
+        assert_eq!(None, stats.items_per_elf);
+    }
+
+    #[test]
+    fn it_folds_totals_without_collecting_packs() {
+        let input = "1000\n2000\n3000\n\n4000\n\n5000\n6000\n\n7000\n8000\n9000\n\n10000\n";
+
+        let mut totals = vec![];
+        let elves = super::Calories::fold_reader(
+            input.as_bytes(),
+            super::ParseMode::Strict,
+            |idx, total| totals.push((idx, total)),
+        );
+        assert_eq!(Ok(5), elves);
+        assert_eq!(
+            vec![(0, 6000), (1, 4000), (2, 11000), (3, 24000), (4, 10000)],
+            totals
+        );
+
+        let top = super::Calories::top_n_from_reader(input.as_bytes(), super::ParseMode::Strict, 3);
+        assert_eq!(Ok(vec![(3, 24000), (2, 11000), (4, 10000)]), top);
+        assert!(super::Calories::top_n_from_reader(
+            "1\nx\n".as_bytes(),
+            super::ParseMode::Strict,
+            usize::MAX
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn it_reports_line_numbers_when_streaming() {
+        let input = "1000\n\nabc\n";
+
+        let result = super::Calories::from_reader(
+            input.as_bytes(),
+            super::ParseMode::Strict,
+            super::Retain::TotalsOnly,
+        );
+        assert_eq!(
+            Err(super::ParseError::InvalidLine {
+                line: 3,
+                content: String::from("abc"),
+            }),
+            result
+        );
     }
 }
@@ -1,23 +1,381 @@
-use std::collections::HashSet;
+use std::{cmp::Reverse, fmt};
 
 use itertools::Itertools;
 
-fn parse_range(range: &str) -> HashSet<i32> {
-    let pieces: Vec<i32> = range
-        .split('-')
-        .map(|r| r.parse::<i32>().unwrap())
-        .collect();
-    (pieces[0]..=pieces[1]).collect()
+// Inclusive range of section numbers, compared arithmetically rather than materialised
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Interval {
+    pub start: i32,
+    pub end: i32,
 }
 
-pub fn range_overlaps(range: &str) -> bool {
-    let (a, b) = range.split(',').map(parse_range).collect_tuple().unwrap();
+impl Interval {
+    pub fn new(start: i32, end: i32) -> Interval {
+        assert!(
+            start <= end,
+            "Interval {}-{} ends before it starts",
+            start,
+            end
+        );
+        Interval { start, end }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.start.abs_diff(self.end) as u64 + 1
+    }
+
+    pub fn contains(&self, other: &Interval) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    pub fn overlaps(&self, other: &Interval) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    pub fn intersection(&self, other: &Interval) -> Option<Interval> {
+        if self.overlaps(other) {
+            Some(Interval::new(
+                self.start.max(other.start),
+                self.end.min(other.end),
+            ))
+        } else {
+            None
+        }
+    }
+
+    // Only defined when the result is still a single interval, i.e. overlapping or adjacent
+    pub fn union(&self, other: &Interval) -> Option<Interval> {
+        let touching =
+            self.start <= other.end.saturating_add(1) && other.start <= self.end.saturating_add(1);
+        if touching {
+            Some(Interval::new(
+                self.start.min(other.start),
+                self.end.max(other.end),
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CleaningError {
+    MalformedRange(String),
+    BackwardsRange(String),
+    EmptyLine,
+}
+
+impl fmt::Display for CleaningError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CleaningError::MalformedRange(range) => write!(f, "malformed range '{}'", range),
+            CleaningError::BackwardsRange(range) => {
+                write!(f, "range '{}' ends before it starts", range)
+            }
+            CleaningError::EmptyLine => write!(f, "no ranges on line"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct LineError {
+    pub line: usize,
+    pub error: CleaningError,
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
+    }
+}
+
+fn parse_range(range: &str) -> Result<Interval, CleaningError> {
+    let malformed = || CleaningError::MalformedRange(range.to_string());
+    let (start, end) = range.trim().split_once('-').ok_or_else(malformed)?;
+    let start = start.parse::<i32>().map_err(|_| malformed())?;
+    let end = end.parse::<i32>().map_err(|_| malformed())?;
+    if start > end {
+        return Err(CleaningError::BackwardsRange(range.to_string()));
+    }
+    Ok(Interval::new(start, end))
+}
+
+// Any number of comma-separated ranges assigned together on one line
+#[derive(Debug, PartialEq, Eq)]
+pub struct Group {
+    pub ranges: Vec<Interval>,
+}
+
+impl Group {
+    pub fn parse(line: &str) -> Result<Group, CleaningError> {
+        if line.trim().is_empty() {
+            return Err(CleaningError::EmptyLine);
+        }
+        Ok(Group {
+            ranges: line.split(',').map(parse_range).try_collect()?,
+        })
+    }
+
+    fn pairs(&self) -> impl Iterator<Item = ((usize, &Interval), (usize, &Interval))> {
+        self.ranges.iter().enumerate().tuple_combinations()
+    }
+
+    pub fn any_overlap(&self) -> bool {
+        let mut furthest: Option<i32> = None;
+        for interval in self.ranges.iter().sorted_by_key(|interval| interval.start) {
+            if furthest.is_some_and(|end| interval.start <= end) {
+                return true;
+            }
+            furthest = furthest.max(Some(interval.end));
+        }
+        false
+    }
+
+    pub fn any_contained(&self) -> bool {
+        self.pairs()
+            .any(|((_, a), (_, b))| a.contains(b) || b.contains(a))
+    }
+
+    // The sections shared by every range on the line
+    pub fn common(&self) -> Option<Interval> {
+        self.ranges
+            .iter()
+            .map(|interval| Some(*interval))
+            .reduce(|acc, interval| acc?.intersection(&interval?))
+            .flatten()
+    }
+
+    // The pair of ranges sharing the most sections, earliest pair first on ties
+    pub fn largest_overlap(&self) -> Option<(usize, usize, Interval)> {
+        self.pairs()
+            .filter_map(|((i, a), (j, b))| Some((i, j, a.intersection(b)?)))
+            .max_by_key(|(i, j, shared)| (shared.len(), Reverse((*i, *j))))
+    }
+}
+
+pub fn range_overlaps(range: &str) -> Result<bool, CleaningError> {
+    Ok(Group::parse(range)?.any_overlap())
+}
+
+pub fn range_contains(range: &str) -> Result<bool, CleaningError> {
+    Ok(Group::parse(range)?.any_contained())
+}
+
+#[derive(Debug, PartialEq, Eq, Default)]
+pub struct Schedule {
+    pub assignments: Vec<Interval>,
+}
+
+impl Schedule {
+    // Every range on every line is one elf, numbered in reading order
+    pub fn parse(input: &str) -> Result<Schedule, LineError> {
+        let mut assignments = vec![];
+        for (idx, line) in input.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let group = Group::parse(line).map_err(|error| LineError {
+                line: idx + 1,
+                error,
+            })?;
+            assignments.extend(group.ranges);
+        }
+        Ok(Schedule { assignments })
+    }
+
+    fn sorted(&self) -> Vec<(usize, Interval)> {
+        self.assignments
+            .iter()
+            .copied()
+            .enumerate()
+            .sorted_by_key(|(_, interval)| (interval.start, interval.end))
+            .collect()
+    }
+
+    pub fn covered(&self) -> Vec<Interval> {
+        let mut merged: Vec<Interval> = vec![];
+        for (_, interval) in self.sorted() {
+            match merged.last_mut().and_then(|last| last.union(&interval)) {
+                Some(union) => *merged.last_mut().unwrap() = union,
+                None => merged.push(interval),
+            }
+        }
+        merged
+    }
+
+    pub fn covered_sections(&self) -> u64 {
+        self.covered().iter().map(Interval::len).sum()
+    }
+
+    // Gaps between the first and last covered section
+    pub fn uncovered(&self) -> Vec<Interval> {
+        self.covered()
+            .iter()
+            .tuple_windows()
+            .map(|(a, b)| Interval::new(a.end + 1, b.start - 1))
+            .collect()
+    }
+
+    // The first run of sections covered by the most elves, with that count
+    pub fn most_covered(&self) -> Option<(Interval, usize)> {
+        let events = self
+            .assignments
+            .iter()
+            .flat_map(|interval| [(interval.start as i64, 1), (interval.end as i64 + 1, -1)])
+            .sorted()
+            .collect_vec();
+
+        let mut depth: i64 = 0;
+        let mut best: Option<(Interval, usize)> = None;
+        for (idx, (position, change)) in events.iter().enumerate() {
+            depth += change;
+            let next = match events.get(idx + 1) {
+                Some((next, _)) if next > position => *next,
+                _ => continue,
+            };
+            if depth > 0 && best.is_none_or(|(_, count)| depth as usize > count) {
+                let interval = Interval::new(*position as i32, (next - 1) as i32);
+                best = Some((interval, depth as usize));
+            }
+        }
+        best
+    }
+
+    // For each elf, the other elves whose assignments overlap theirs
+    pub fn overlapping(&self) -> Vec<Vec<usize>> {
+        let mut overlaps = vec![vec![]; self.assignments.len()];
+        let mut active: Vec<(usize, Interval)> = vec![];
+        for (elf, interval) in self.sorted() {
+            active.retain(|(_, other)| other.end >= interval.start);
+            for (other, _) in active.iter() {
+                overlaps[elf].push(*other);
+                overlaps[*other].push(elf);
+            }
+            active.push((elf, interval));
+        }
+        for others in overlaps.iter_mut() {
+            others.sort();
+        }
+        overlaps
+    }
+}
+
+// A floor area assigned as `x1-x2:y1-y2`, inclusive on both axes
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Rect {
+    pub x: Interval,
+    pub y: Interval,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Comparison {
+    pub overlaps: bool,
+    pub contains: bool,
+    pub intersection_area: u64,
+}
+
+impl Rect {
+    pub fn parse(area: &str) -> Result<Rect, CleaningError> {
+        let (x, y) = area
+            .trim()
+            .split_once(':')
+            .ok_or_else(|| CleaningError::MalformedRange(area.to_string()))?;
+        Ok(Rect {
+            x: parse_range(x)?,
+            y: parse_range(y)?,
+        })
+    }
+
+    pub fn area(&self) -> u64 {
+        self.x.len() * self.y.len()
+    }
+
+    pub fn contains(&self, other: &Rect) -> bool {
+        self.x.contains(&other.x) && self.y.contains(&other.y)
+    }
+
+    pub fn overlaps(&self, other: &Rect) -> bool {
+        self.x.overlaps(&other.x) && self.y.overlaps(&other.y)
+    }
+
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        Some(Rect {
+            x: self.x.intersection(&other.x)?,
+            y: self.y.intersection(&other.y)?,
+        })
+    }
+
+    pub fn compare(&self, other: &Rect) -> Comparison {
+        Comparison {
+            overlaps: self.overlaps(other),
+            contains: self.contains(other) || other.contains(self),
+            intersection_area: self.intersection(other).map_or(0, |rect| rect.area()),
+        }
+    }
+}
 
-    !a.is_disjoint(&b)
+#[derive(Debug, PartialEq, Eq, Default)]
+pub struct FloorPlan {
+    pub areas: Vec<Rect>,
+}
+
+impl FloorPlan {
+    pub fn parse(input: &str) -> Result<FloorPlan, LineError> {
+        let mut areas = vec![];
+        for (idx, line) in input.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            for area in line.split(',') {
+                areas.push(Rect::parse(area).map_err(|error| LineError {
+                    line: idx + 1,
+                    error,
+                })?);
+            }
+        }
+        Ok(FloorPlan { areas })
+    }
+
+    pub fn comparisons(&self) -> Vec<(usize, usize, Comparison)> {
+        self.areas
+            .iter()
+            .enumerate()
+            .tuple_combinations()
+            .map(|((i, a), (j, b))| (i, j, a.compare(b)))
+            .collect()
+    }
+
+    // Sweeps vertical slabs between x boundaries, merging the y ranges crossing each one
+    pub fn covered_area(&self) -> u64 {
+        let edges = self
+            .areas
+            .iter()
+            .flat_map(|rect| [rect.x.start as i64, rect.x.end as i64 + 1])
+            .sorted()
+            .dedup()
+            .collect_vec();
+
+        edges
+            .iter()
+            .tuple_windows()
+            .map(|(left, right)| {
+                let column = Schedule {
+                    assignments: self
+                        .areas
+                        .iter()
+                        .filter(|rect| rect.x.start as i64 <= *left && *left <= rect.x.end as i64)
+                        .map(|rect| rect.y)
+                        .collect(),
+                };
+                column.covered_sections() * (right - left) as u64
+            })
+            .sum()
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::{CleaningError, FloorPlan, Group, Interval, LineError, Rect, Schedule};
 
     #[test]
     fn it_returns_true_if_one_range_contains_another() {
@@ -31,6 +389,144 @@ mod tests {
             "50-50,1-49",
         ];
         let expectations = [false, false, true, true, true, true, false];
-        assert_eq!(expectations, inputs.map(super::range_overlaps));
+        assert_eq!(expectations.map(Ok), inputs.map(super::range_overlaps));
+    }
+
+    #[test]
+    fn it_detects_full_containment() {
+        let inputs = [
+            "2-4,6-8", "2-3,4-5", "5-7,7-9", "2-8,3-7", "6-6,4-6", "2-6,4-8",
+        ];
+        let expectations = [false, false, false, true, true, false];
+        assert_eq!(expectations.map(Ok), inputs.map(super::range_contains));
+    }
+
+    #[test]
+    fn it_reports_malformed_ranges_instead_of_panicking() {
+        let malformed = Err(CleaningError::MalformedRange(String::from("x")));
+        assert_eq!(malformed, super::range_overlaps("1-2,x"));
+        assert_eq!(malformed, super::range_contains("1-2,x"));
+    }
+
+    #[test]
+    fn it_combines_intervals_without_materialising_them() {
+        let huge = Interval::new(1, 1_000_000_000);
+        let small = Interval::new(5, 10);
+
+        assert_eq!(1_000_000_000, huge.len());
+        assert!(huge.contains(&small));
+        assert_eq!(Some(small), huge.intersection(&small));
+        assert_eq!(None, small.intersection(&Interval::new(11, 20)));
+        assert_eq!(
+            Some(Interval::new(5, 20)),
+            small.union(&Interval::new(11, 20))
+        );
+        assert_eq!(None, small.union(&Interval::new(12, 20)));
+    }
+
+    #[test]
+    fn it_merges_the_whole_schedule() {
+        let schedule = Schedule::parse("2-4,6-8\n12-15,5-5\n20-20,14-16\n").unwrap();
+
+        assert_eq!(
+            vec![
+                Interval::new(2, 8),
+                Interval::new(12, 16),
+                Interval::new(20, 20)
+            ],
+            schedule.covered()
+        );
+        assert_eq!(13, schedule.covered_sections());
+        assert_eq!(
+            vec![Interval::new(9, 11), Interval::new(17, 19)],
+            schedule.uncovered()
+        );
+        assert_eq!(Some((Interval::new(14, 15), 2)), schedule.most_covered());
+    }
+
+    #[test]
+    fn it_lists_overlapping_elves() {
+        let schedule = Schedule::parse("2-4,6-8\n2-8,3-7\n6-6,4-6").unwrap();
+
+        assert_eq!(
+            vec![
+                vec![2, 3, 5],
+                vec![2, 3, 4, 5],
+                vec![0, 1, 3, 4, 5],
+                vec![0, 1, 2, 4, 5],
+                vec![1, 2, 3, 5],
+                vec![0, 1, 2, 3, 4],
+            ],
+            schedule.overlapping()
+        );
+        assert_eq!(Some((Interval::new(6, 6), 5)), schedule.most_covered());
+        assert!(Schedule::default().most_covered().is_none());
+    }
+
+    #[test]
+    fn it_handles_lines_with_many_ranges() {
+        let group = Group::parse("1-10,4-6,5-12,20-30").unwrap();
+
+        assert!(group.any_overlap());
+        assert!(group.any_contained());
+        assert_eq!(None, group.common());
+        assert_eq!(Some((0, 2, Interval::new(5, 10))), group.largest_overlap());
+
+        let group = Group::parse("1-10,4-6,5-12").unwrap();
+        assert_eq!(Some(Interval::new(5, 6)), group.common());
+
+        let group = Group::parse("1-2,4-6,8-12").unwrap();
+        assert!(!group.any_overlap());
+        assert_eq!(None, group.largest_overlap());
+    }
+
+    #[test]
+    fn it_names_the_malformed_range() {
+        assert_eq!(
+            Err(CleaningError::MalformedRange(String::from("4-x"))),
+            Group::parse("1-2,4-x,5-6")
+        );
+        assert_eq!(
+            Err(CleaningError::BackwardsRange(String::from("9-3"))),
+            Group::parse("9-3")
+        );
+        assert_eq!(
+            Err(LineError {
+                line: 2,
+                error: CleaningError::MalformedRange(String::from("3")),
+            }),
+            Schedule::parse("1-2,3-4\n1-2,3\n")
+        );
+    }
+
+    #[test]
+    fn it_compares_rectangles() {
+        let a = Rect::parse("1-4:1-4").unwrap();
+        let b = Rect::parse("3-6:3-6").unwrap();
+        let c = Rect::parse("2-3:2-2").unwrap();
+
+        let comparison = a.compare(&b);
+        assert!(comparison.overlaps);
+        assert!(!comparison.contains);
+        assert_eq!(4, comparison.intersection_area);
+
+        let comparison = c.compare(&a);
+        assert!(comparison.contains);
+        assert_eq!(2, comparison.intersection_area);
+
+        assert_eq!(
+            Err(CleaningError::MalformedRange(String::from("1-4"))),
+            Rect::parse("1-4")
+        );
+    }
+
+    #[test]
+    fn it_measures_the_covered_floor_area() {
+        let plan = FloorPlan::parse("1-4:1-4,3-6:3-6\n2-3:2-2\n10-10:10-11").unwrap();
+
+        assert_eq!(16 + 16 - 4 + 2, plan.covered_area());
+        assert_eq!(6, plan.comparisons().len());
+        assert_eq!(Ok(FloorPlan::default()), FloorPlan::parse(""));
+        assert_eq!(0, FloorPlan::default().covered_area());
     }
 }
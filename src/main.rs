@@ -1,4 +1,5 @@
-use std::fs;
+use std::fs::{self, File};
+use std::io::BufReader;
 use std::path::Path;
 
 use communication::FindUnique;
@@ -21,46 +22,129 @@ mod crt;
 mod monkeys;
 
 fn main() {
-    if let Some(calorie_lines) = read_file(Path::new("./src/inputs/calories.txt")) {
-        let mut cals = calories::Calories::from_str(&calorie_lines);
-        let max = cals.max();
-        println!("Most calories: {}", max);
-        println!("Total of top 3 elves: {}", cals.total_n(3));
+    if let Some(calorie_reader) = open_file(Path::new("./src/inputs/calories.txt")) {
+        match calories::Calories::from_reader(
+            calorie_reader,
+            calories::ParseMode::Lenient,
+            calories::Retain::Food,
+        ) {
+            Ok(cals) => {
+                match (cals.max(), cals.total_n(3)) {
+                    (Ok(max), Ok(total)) => {
+                        println!("Most calories: {}", max);
+                        println!("Total of top 3 elves: {}", total);
+                    }
+                    (Err(err), _) | (_, Err(err)) => println!("Invalid calorie input: {}", err),
+                }
+                for (idx, total) in cals.top_n(3) {
+                    println!("Elf {} is carrying {}", idx + 1, total);
+                }
+                if let Some(stats) = cals.statistics(10) {
+                    print!("{}", stats);
+                }
+            }
+            Err(err) => println!("Invalid calorie input: {}", err),
+        }
     }
 
     if let Some(rock_paper_scissors) = read_file(Path::new("./src/inputs/rock-paper-scissors.txt"))
     {
-        let game = rps::Game::from_str(&rock_paper_scissors);
-        println!("Total score: {}", game.total_score());
+        let rules = rps::Rules::classic();
+        match rps::Game::parse(&rock_paper_scissors, &rps::Cipher::outcomes(), &rules) {
+            Ok(game) => {
+                let report = game.report(&rules);
+                println!("Total score: {}", report.total);
+                println!(
+                    "Wins: {}, draws: {}, losses: {}",
+                    report.wins, report.draws, report.losses
+                );
+            }
+            Err(err) => println!("Invalid strategy guide: {}", err),
+        }
+        if let Ok(results) = rps::brute_force(&rock_paper_scissors, &rps::Cipher::shapes(), &rules)
+        {
+            for (cipher, score) in results {
+                println!("{}: {}", cipher.describe(&rules), score);
+            }
+        }
     }
 
     if let Some(rucksacks) = read_file(Path::new("./src/inputs/rucksacks.txt")) {
-        let total_score = rucksack::score_lines(rucksacks.split('\n').map(String::from).collect());
-        let badge_score =
-            rucksack::group_and_score(rucksacks.split('\n').map(String::from).collect());
-        println!("Total elf score: {}", total_score);
-        println!("Total badge score: {}", badge_score);
+        let lines = rucksacks.lines().map(String::from).collect_vec();
+        let priorities = rucksack::Priorities::default();
+        match rucksack::score_lines_with(&lines, 2, &priorities) {
+            Ok(total_score) => println!("Total elf score: {}", total_score),
+            Err(err) => println!("Invalid rucksack: {}", err),
+        }
+        match rucksack::group_and_score_with(&lines, 3, 2, &priorities) {
+            Ok(badge_score) => println!("Total badge score: {}", badge_score),
+            Err(err) => println!("Invalid elf group: {}", err),
+        }
     }
 
     if let Some(schedule) = read_file(Path::new("./src/inputs/cleaning-schedule.txt")) {
-        let overlaps = schedule.split('\n').filter(|a| {
-            if a.is_empty() {
-                false
-            } else {
-                cleaning::range_overlaps(a)
+        let count_matching = |check: fn(&str) -> Result<bool, cleaning::CleaningError>| {
+            schedule
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(check)
+                .try_fold(0, |count, matched| matched.map(|m| count + usize::from(m)))
+        };
+        match count_matching(cleaning::range_overlaps) {
+            Ok(count) => println!("Total cleaning schedules with overlaps: {}", count),
+            Err(error) => println!("Invalid cleaning line: {}", error),
+        }
+        match count_matching(cleaning::range_contains) {
+            Ok(count) => println!(
+                "Total cleaning schedules fully containing another: {}",
+                count
+            ),
+            Err(error) => println!("Invalid cleaning line: {}", error),
+        }
+
+        match cleaning::Schedule::parse(&schedule) {
+            Ok(schedule) => {
+                println!(
+                    "Sections covered: {}, gaps: {}",
+                    schedule.covered_sections(),
+                    schedule.uncovered().len()
+                );
+                if let Some((interval, count)) = schedule.most_covered() {
+                    println!(
+                        "Most covered sections: {}-{} ({} elves)",
+                        interval.start, interval.end, count
+                    );
+                }
             }
-        });
-        println!(
-            "Total cleaning schedules with overlaps: {}",
-            overlaps.count()
-        );
+            Err(error) => println!("Invalid cleaning schedule: {}", error),
+        }
     }
 
     if let Some(crane_ops) = read_file(Path::new("./src/inputs/crane_ops.txt")) {
-        let mut crane = stacks::Crane::from_str(&crane_ops);
-        let output = crane.operate_all();
-
-        println!("{}", output);
+        match stacks::Crane::from_str(&crane_ops) {
+            Ok(crane) => {
+                let validation = crane.validate();
+                for error in validation.errors.iter() {
+                    println!("Invalid crane move on {}", error);
+                }
+                println!("{}", validation.layout);
+
+                // The models would panic on the moves the validator just rejected
+                if validation.errors.is_empty() {
+                    for model in [
+                        stacks::Model::CrateMover9000,
+                        stacks::Model::CrateMover9001,
+                        stacks::Model::capacity(2).unwrap(),
+                    ] {
+                        let mut crane = stacks::Crane::from_str(&crane_ops).unwrap();
+                        let output = crane.operate_all_with(model);
+
+                        println!("{}: {}", model, output);
+                    }
+                }
+            }
+            Err(error) => println!("Invalid crane input on {}", error),
+        }
     }
 
     if let Some(comms) = read_file(Path::new("./src/inputs/communication.txt")) {
@@ -105,6 +189,13 @@ fn main() {
     }
 }
 
+fn open_file(path: &Path) -> Option<BufReader<File>> {
+    if let Ok(file) = File::open(path) {
+        return Some(BufReader::new(file));
+    }
+    None
+}
+
 fn read_file(path: &Path) -> Option<String> {
     if let Ok(lines) = fs::read_to_string(path) {
         return Some(lines);
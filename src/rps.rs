@@ -1,3 +1,8 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use itertools::Itertools;
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Moves {
     Rock,
@@ -5,99 +10,764 @@ pub enum Moves {
     Scissors,
 }
 
+impl Moves {
+    pub const ALL: [Moves; 3] = [Moves::Rock, Moves::Paper, Moves::Scissors];
+}
+
+pub trait Shape: Copy {
+    fn index(&self) -> usize;
+}
+
+impl Shape for Moves {
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl Shape for usize {
+    fn index(&self) -> usize {
+        *self
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Outcome {
+    Loss,
+    Draw,
+    Win,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rules {
+    names: Vec<String>,
+    // beats[a][b] is true when shape a beats shape b
+    beats: Vec<Vec<bool>>,
+    shape_scores: Vec<i32>,
+    win: i32,
+    draw: i32,
+    loss: i32,
+}
+
 #[derive(Debug, PartialEq, Eq)]
-pub struct Round {
-    pub first: Moves,
-    pub second: Moves,
+pub enum RulesError {
+    MissingScores { shapes: usize, scores: usize },
+    UnknownShape(usize),
+    BeatsItself(usize),
+    MutualBeats(usize, usize),
+    EvenCycle(usize),
 }
 
-impl Round {
-    pub fn from_cols(column_a: &str, column_b: &str) -> Round {
-        let first = match column_a {
-            "A" => Moves::Rock,
-            "B" => Moves::Paper,
-            "C" => Moves::Scissors,
-            _ => Moves::Rock,
-        };
-        let second = match column_b {
-            "X" => match first {
-                Moves::Rock => Moves::Scissors,
-                Moves::Paper => Moves::Rock,
-                Moves::Scissors => Moves::Paper,
-            },
-            "Y" => first,
-            "Z" => match first {
-                Moves::Rock => Moves::Paper,
-                Moves::Paper => Moves::Scissors,
-                Moves::Scissors => Moves::Rock,
-            },
-            _ => Moves::Rock,
-        };
-        Round { first, second }
+impl fmt::Display for RulesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RulesError::MissingScores { shapes, scores } => {
+                write!(f, "{} shapes but {} scores", shapes, scores)
+            }
+            RulesError::UnknownShape(shape) => write!(f, "no shape {}", shape),
+            RulesError::BeatsItself(shape) => write!(f, "shape {} beats itself", shape),
+            RulesError::MutualBeats(first, second) => {
+                write!(f, "shapes {} and {} beat each other", first, second)
+            }
+            RulesError::EvenCycle(count) => write!(
+                f,
+                "a balanced cyclic game needs an odd number of shapes, not {}",
+                count
+            ),
+        }
     }
+}
+
+impl Rules {
+    pub fn new(
+        names: Vec<&str>,
+        beats: Vec<(usize, usize)>,
+        shape_scores: Vec<i32>,
+    ) -> Result<Rules, RulesError> {
+        let count = names.len();
+        if count != shape_scores.len() {
+            return Err(RulesError::MissingScores {
+                shapes: count,
+                scores: shape_scores.len(),
+            });
+        }
 
-    fn shape_score(&self, player_move: &Moves) -> i32 {
-        match player_move {
-            Moves::Rock => 1,
-            Moves::Paper => 2,
-            Moves::Scissors => 3,
+        let mut table = vec![vec![false; count]; count];
+        for (winner, loser) in beats {
+            if let Some(unknown) = [winner, loser].into_iter().find(|shape| *shape >= count) {
+                return Err(RulesError::UnknownShape(unknown));
+            }
+            if winner == loser {
+                return Err(RulesError::BeatsItself(winner));
+            }
+            if table[loser][winner] {
+                return Err(RulesError::MutualBeats(winner, loser));
+            }
+            table[winner][loser] = true;
         }
+
+        Ok(Rules {
+            names: names.into_iter().map(String::from).collect(),
+            beats: table,
+            shape_scores,
+            win: 6,
+            draw: 3,
+            loss: 0,
+        })
     }
 
-    fn round_score(&self) -> i32 {
-        match self.first {
-            Moves::Rock => match self.second {
-                Moves::Rock => 3,
-                Moves::Paper => 6,
-                Moves::Scissors => 0,
-            },
-            Moves::Paper => match self.second {
-                Moves::Rock => 0,
-                Moves::Paper => 3,
-                Moves::Scissors => 6,
-            },
-            Moves::Scissors => match self.second {
-                Moves::Rock => 6,
-                Moves::Paper => 0,
-                Moves::Scissors => 3,
-            },
+    pub fn with_points(mut self, win: i32, draw: i32, loss: i32) -> Rules {
+        self.win = win;
+        self.draw = draw;
+        self.loss = loss;
+        self
+    }
+
+    pub fn classic() -> Rules {
+        Rules::new(
+            vec!["Rock", "Paper", "Scissors"],
+            vec![(0, 2), (1, 0), (2, 1)],
+            vec![1, 2, 3],
+        )
+        .expect("the classic rules are valid")
+    }
+
+    pub fn lizard_spock() -> Rules {
+        Rules::new(
+            vec!["Rock", "Paper", "Scissors", "Lizard", "Spock"],
+            vec![
+                (0, 2),
+                (0, 3),
+                (1, 0),
+                (1, 4),
+                (2, 1),
+                (2, 3),
+                (3, 1),
+                (3, 4),
+                (4, 0),
+                (4, 2),
+            ],
+            vec![1, 2, 3, 4, 5],
+        )
+        .expect("the lizard-spock rules are valid")
+    }
+
+    // Each shape beats the (n - 1) / 2 shapes before it, wrapping around
+    pub fn cyclic(count: usize) -> Result<Rules, RulesError> {
+        if count.is_multiple_of(2) {
+            return Err(RulesError::EvenCycle(count));
+        }
+        let names = (1..=count)
+            .map(|i| format!("Shape {}", i))
+            .collect::<Vec<_>>();
+        let beats = (0..count)
+            .flat_map(|winner| {
+                (1..=count / 2).map(move |offset| (winner, (winner + count - offset) % count))
+            })
+            .collect();
+
+        Rules::new(
+            names.iter().map(String::as_str).collect(),
+            beats,
+            (1..=count as i32).collect(),
+        )
+    }
+
+    pub fn shapes(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn name(&self, shape: usize) -> &str {
+        &self.names[shape]
+    }
+
+    pub fn shape_score(&self, shape: usize) -> i32 {
+        self.shape_scores[shape]
+    }
+
+    pub fn outcome(&self, first: usize, second: usize) -> Outcome {
+        if self.beats[second][first] {
+            Outcome::Win
+        } else if self.beats[first][second] {
+            Outcome::Loss
+        } else {
+            Outcome::Draw
+        }
+    }
+
+    pub fn outcome_score(&self, outcome: Outcome) -> i32 {
+        match outcome {
+            Outcome::Win => self.win,
+            Outcome::Draw => self.draw,
+            Outcome::Loss => self.loss,
+        }
+    }
+
+    pub fn shape_for(&self, first: usize, outcome: Outcome) -> Option<usize> {
+        (0..self.shapes()).find(|second| self.outcome(first, *second) == outcome)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Symbol {
+    Shape(usize),
+    Outcome(Outcome),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CipherError {
+    UnknownSymbol(String),
+    MissingColumn,
+    NoShapeFor(Outcome),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct GuideError {
+    pub line: usize,
+    pub error: CipherError,
+}
+
+impl fmt::Display for GuideError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.error {
+            CipherError::UnknownSymbol(symbol) => {
+                write!(f, "line {}: unknown symbol {:?}", self.line, symbol)
+            }
+            CipherError::MissingColumn => write!(f, "line {}: expected two columns", self.line),
+            CipherError::NoShapeFor(outcome) => {
+                write!(f, "line {}: no shape gives a {:?}", self.line, outcome)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cipher {
+    first: BTreeMap<String, usize>,
+    second: BTreeMap<String, Symbol>,
+}
+
+impl Cipher {
+    pub fn new(first: Vec<(&str, usize)>, second: Vec<(&str, Symbol)>) -> Cipher {
+        Cipher {
+            first: first
+                .into_iter()
+                .map(|(k, v)| (String::from(k), v))
+                .collect(),
+            second: second
+                .into_iter()
+                .map(|(k, v)| (String::from(k), v))
+                .collect(),
         }
     }
 
+    pub fn shapes() -> Cipher {
+        Cipher::new(
+            vec![("A", 0), ("B", 1), ("C", 2)],
+            vec![
+                ("X", Symbol::Shape(0)),
+                ("Y", Symbol::Shape(1)),
+                ("Z", Symbol::Shape(2)),
+            ],
+        )
+    }
+
+    pub fn outcomes() -> Cipher {
+        Cipher::new(
+            vec![("A", 0), ("B", 1), ("C", 2)],
+            vec![
+                ("X", Symbol::Outcome(Outcome::Loss)),
+                ("Y", Symbol::Outcome(Outcome::Draw)),
+                ("Z", Symbol::Outcome(Outcome::Win)),
+            ],
+        )
+    }
+
+    pub fn decode(
+        &self,
+        column_a: &str,
+        column_b: &str,
+        rules: &Rules,
+    ) -> Result<Round<usize>, CipherError> {
+        let first = *self
+            .first
+            .get(column_a)
+            .filter(|shape| **shape < rules.shapes())
+            .ok_or_else(|| CipherError::UnknownSymbol(String::from(column_a)))?;
+        let second = match self.second.get(column_b) {
+            Some(Symbol::Shape(shape)) if *shape < rules.shapes() => *shape,
+            Some(Symbol::Outcome(outcome)) => rules
+                .shape_for(first, *outcome)
+                .ok_or(CipherError::NoShapeFor(*outcome))?,
+            _ => return Err(CipherError::UnknownSymbol(String::from(column_b))),
+        };
+        Ok(Round { first, second })
+    }
+
+    pub fn mapping(&self) -> Vec<(&str, Symbol)> {
+        self.second.iter().map(|(k, v)| (k.as_str(), *v)).collect()
+    }
+
+    pub fn describe(&self, rules: &Rules) -> String {
+        self.second
+            .iter()
+            .map(|(symbol, target)| match target {
+                Symbol::Shape(shape) => format!("{}={}", symbol, rules.name(*shape)),
+                Symbol::Outcome(outcome) => format!("{}={:?}", symbol, outcome),
+            })
+            .join(" ")
+    }
+
+    pub fn permutations(&self) -> Vec<Cipher> {
+        let symbols = self.second.keys().collect::<Vec<_>>();
+        self.second
+            .values()
+            .copied()
+            .permutations(symbols.len())
+            .map(|targets| Cipher {
+                first: self.first.clone(),
+                second: symbols
+                    .iter()
+                    .map(|k| String::from(*k))
+                    .zip(targets)
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+pub fn brute_force(
+    input: &str,
+    cipher: &Cipher,
+    rules: &Rules,
+) -> Result<Vec<(Cipher, i32)>, GuideError> {
+    cipher
+        .permutations()
+        .into_iter()
+        .map(|candidate| {
+            let score = Game::parse(input, &candidate, rules)?.total_score_with(rules);
+            Ok((candidate, score))
+        })
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Round<S = Moves> {
+    pub first: S,
+    pub second: S,
+}
+
+impl Round {
+    pub fn from_cols(column_a: &str, column_b: &str) -> Result<Round, CipherError> {
+        let round = Cipher::outcomes().decode(column_a, column_b, &Rules::classic())?;
+        Ok(Round {
+            first: Moves::ALL[round.first],
+            second: Moves::ALL[round.second],
+        })
+    }
+
     pub fn score(&self) -> i32 {
-        let shape_score = self.shape_score(&self.second);
-        let game_score = self.round_score();
+        self.score_with(&Rules::classic())
+    }
+}
+
+impl<S: Shape> Round<S> {
+    pub fn outcome_with(&self, rules: &Rules) -> Outcome {
+        rules.outcome(self.first.index(), self.second.index())
+    }
+
+    pub fn score_with(&self, rules: &Rules) -> i32 {
+        let shape_score = rules.shape_score(self.second.index());
+        let game_score = rules.outcome_score(self.outcome_with(rules));
 
         shape_score + game_score
     }
 }
 
 #[derive(Debug, PartialEq, Eq)]
-pub struct Game {
-    rounds: Vec<Round>,
+pub struct Game<S = Moves> {
+    rounds: Vec<Round<S>>,
 }
 
 impl Game {
-    pub fn from_str(input: &str) -> Game {
-        let mut rounds: Vec<Round> = vec![];
-        for line in input.split('\n') {
-            if !line.is_empty() {
-                let cols: Vec<&str> = line.split(' ').take(2).collect();
-                rounds.push(Round::from_cols(cols[0], cols[1]));
+    pub fn from_str(input: &str) -> Result<Game, GuideError> {
+        let game = Game::parse(input, &Cipher::outcomes(), &Rules::classic())?;
+        let rounds = game
+            .rounds
+            .iter()
+            .map(|r| Round {
+                first: Moves::ALL[r.first],
+                second: Moves::ALL[r.second],
+            })
+            .collect();
+
+        Ok(Game { rounds })
+    }
+
+    pub fn total_score(&self) -> i32 {
+        self.total_score_with(&Rules::classic())
+    }
+}
+
+impl Game<usize> {
+    pub fn parse(input: &str, cipher: &Cipher, rules: &Rules) -> Result<Game<usize>, GuideError> {
+        let mut rounds: Vec<Round<usize>> = vec![];
+        for (idx, line) in input.split('\n').enumerate() {
+            if !line.trim().is_empty() {
+                let mut cols = line.split_whitespace();
+                let round = match (cols.next(), cols.next()) {
+                    (Some(column_a), Some(column_b)) => cipher.decode(column_a, column_b, rules),
+                    _ => Err(CipherError::MissingColumn),
+                };
+                rounds.push(round.map_err(|error| GuideError {
+                    line: idx + 1,
+                    error,
+                })?);
             }
         }
 
+        Ok(Game { rounds })
+    }
+}
+
+impl<S: Shape> Game<S> {
+    pub fn from_rounds(rounds: Vec<Round<S>>) -> Game<S> {
         Game { rounds }
     }
 
-    pub fn total_score(&self) -> i32 {
-        self.rounds.iter().map(|r| r.score()).sum()
+    pub fn total_score_with(&self, rules: &Rules) -> i32 {
+        self.rounds.iter().map(|r| r.score_with(rules)).sum()
+    }
+
+    pub fn opponent_moves(&self) -> Vec<S> {
+        self.rounds.iter().map(|r| r.first).collect()
+    }
+
+    pub fn player_moves(&self) -> Vec<S> {
+        self.rounds.iter().map(|r| r.second).collect()
+    }
+
+    pub fn report(&self, rules: &Rules) -> Report {
+        let mut report = Report::default();
+        for round in self.rounds.iter() {
+            let outcome = round.outcome_with(rules);
+            let shape_points = rules.shape_score(round.second.index());
+            let outcome_points = rules.outcome_score(outcome);
+            match outcome {
+                Outcome::Win => report.wins += 1,
+                Outcome::Draw => report.draws += 1,
+                Outcome::Loss => report.losses += 1,
+            }
+            report.total += shape_points + outcome_points;
+            report.rounds.push(RoundReport {
+                opponent: round.first.index(),
+                player: round.second.index(),
+                outcome,
+                shape_points,
+                outcome_points,
+                running_score: report.total,
+            });
+        }
+        report
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct RoundReport {
+    pub opponent: usize,
+    pub player: usize,
+    pub outcome: Outcome,
+    pub shape_points: i32,
+    pub outcome_points: i32,
+    pub running_score: i32,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    pub rounds: Vec<RoundReport>,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    pub total: i32,
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        String::from(value)
+    }
+}
+
+impl Report {
+    pub fn to_csv(&self, rules: &Rules) -> String {
+        let mut csv = String::from(
+            "round,opponent,player,outcome,shape_points,outcome_points,running_score\n",
+        );
+        for (idx, round) in self.rounds.iter().enumerate() {
+            csv += &format!(
+                "{},{},{},{:?},{},{},{}\n",
+                idx + 1,
+                csv_field(rules.name(round.opponent)),
+                csv_field(rules.name(round.player)),
+                round.outcome,
+                round.shape_points,
+                round.outcome_points,
+                round.running_score
+            );
+        }
+        csv
     }
 }
 
+// Each player scores their shape once, plus the outcome against every other player
+pub fn score_players(shapes: &[usize], rules: &Rules) -> Vec<i32> {
+    shapes
+        .iter()
+        .enumerate()
+        .map(|(idx, shape)| {
+            let outcomes: i32 = shapes
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != idx)
+                .map(|(_, other_shape)| rules.outcome_score(rules.outcome(*other_shape, *shape)))
+                .sum();
+            rules.shape_score(*shape) + outcomes
+        })
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MismatchError {
+    PlayerCount {
+        round: usize,
+        expected: usize,
+        found: usize,
+    },
+    GuideLength {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for MismatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MismatchError::PlayerCount {
+                round,
+                expected,
+                found,
+            } => write!(
+                f,
+                "round {}: expected {} players, found {}",
+                round, expected, found
+            ),
+            MismatchError::GuideLength {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "guide {:?}: expected {} rounds, found {}",
+                name, expected, found
+            ),
+        }
+    }
+}
+
+pub fn score_multi_game(rounds: &[Vec<usize>], rules: &Rules) -> Result<Vec<i32>, MismatchError> {
+    let players = rounds.first().map_or(0, |round| round.len());
+    let mut totals = vec![0; players];
+    for (idx, round) in rounds.iter().enumerate() {
+        if round.len() != players {
+            return Err(MismatchError::PlayerCount {
+                round: idx + 1,
+                expected: players,
+                found: round.len(),
+            });
+        }
+        for (total, score) in totals.iter_mut().zip(score_players(round, rules)) {
+            *total += score;
+        }
+    }
+    Ok(totals)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Standing {
+    pub name: String,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    pub points: i32,
+}
+
+#[derive(Debug)]
+pub struct Tournament {
+    rules: Rules,
+    guides: Vec<(String, Vec<usize>)>,
+}
+
+impl Tournament {
+    pub fn new(rules: Rules) -> Tournament {
+        Tournament {
+            rules,
+            guides: vec![],
+        }
+    }
+
+    // Every guide plays every other round by round, so they must all be the same length
+    pub fn add_guide(&mut self, name: &str, moves: Vec<usize>) -> Result<(), MismatchError> {
+        if let Some((_, first)) = self.guides.first() {
+            if first.len() != moves.len() {
+                return Err(MismatchError::GuideLength {
+                    name: String::from(name),
+                    expected: first.len(),
+                    found: moves.len(),
+                });
+            }
+        }
+        self.guides.push((String::from(name), moves));
+        Ok(())
+    }
+
+    pub fn standings(&self) -> Vec<Standing> {
+        let mut standings = self
+            .guides
+            .iter()
+            .map(|(name, _)| Standing {
+                name: name.clone(),
+                wins: 0,
+                draws: 0,
+                losses: 0,
+                points: 0,
+            })
+            .collect::<Vec<_>>();
+
+        for (a, b) in (0..self.guides.len()).tuple_combinations() {
+            for (player, opponent) in [(a, b), (b, a)] {
+                let rounds = self.guides[opponent]
+                    .1
+                    .iter()
+                    .zip(self.guides[player].1.iter())
+                    .map(|(first, second)| Round {
+                        first: *first,
+                        second: *second,
+                    })
+                    .collect();
+                let report = Game::from_rounds(rounds).report(&self.rules);
+                let standing = &mut standings[player];
+                standing.wins += report.wins;
+                standing.draws += report.draws;
+                standing.losses += report.losses;
+                standing.points += report.total;
+            }
+        }
+
+        standings.sort_by(|a, b| b.points.cmp(&a.points).then(b.wins.cmp(&a.wins)));
+        standings
+    }
+
+    pub fn table(&self) -> String {
+        let mut table = format!(
+            "{:<16} {:>6} {:>6} {:>6} {:>8}\n",
+            "Guide", "Won", "Drawn", "Lost", "Points"
+        );
+        for standing in self.standings() {
+            table += &format!(
+                "{:<16} {:>6} {:>6} {:>6} {:>8}\n",
+                standing.name, standing.wins, standing.draws, standing.losses, standing.points
+            );
+        }
+        table
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Constraints {
+    pub max_wins: Option<usize>,
+    pub no_repeats: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Plan {
+    pub moves: Vec<Moves>,
+    pub score: i32,
+}
+
+// Best score so far, plus the shape played in the previous round and the wins used before it
+type Step = Option<(i32, usize, usize)>;
+
+pub fn optimise(opponent: &[Moves], constraints: &Constraints) -> Plan {
+    let rules = Rules::classic();
+    let win_slots = constraints
+        .max_wins
+        .map_or(1, |k| k.min(opponent.len()) + 1);
+    let mut table: Vec<Vec<Vec<Step>>> = vec![vec![vec![None; win_slots]; 3]; opponent.len()];
+
+    for (idx, first) in opponent.iter().enumerate() {
+        for (shape, second) in Moves::ALL.iter().enumerate() {
+            let round = Round {
+                first: *first,
+                second: *second,
+            };
+            let won = round.outcome_with(&rules) == Outcome::Win;
+            let counted = (won && constraints.max_wins.is_some()) as usize;
+
+            if idx == 0 {
+                if counted < win_slots {
+                    table[0][shape][counted] = Some((round.score_with(&rules), 0, 0));
+                }
+                continue;
+            }
+
+            for prev in 0..3 {
+                if constraints.no_repeats && prev == shape {
+                    continue;
+                }
+                for wins in 0..win_slots - counted {
+                    if let Some((score, _, _)) = table[idx - 1][prev][wins] {
+                        let candidate = score + round.score_with(&rules);
+                        let slot = &mut table[idx][shape][wins + counted];
+                        if slot.is_none_or(|(best, _, _)| candidate > best) {
+                            *slot = Some((candidate, prev, wins));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let best = table.last().and_then(|last| {
+        (0..3)
+            .flat_map(|shape| (0..win_slots).map(move |wins| (shape, wins)))
+            .filter_map(|(shape, wins)| last[shape][wins].map(|(score, _, _)| (score, shape, wins)))
+            .max_by_key(|(score, _, _)| *score)
+    });
+
+    let mut moves = vec![];
+    let mut score = 0;
+    if let Some((total, mut shape, mut wins)) = best {
+        score = total;
+        for idx in (0..opponent.len()).rev() {
+            moves.push(Moves::ALL[shape]);
+            let (_, prev, prev_wins) = table[idx][shape][wins].unwrap();
+            shape = prev;
+            wins = prev_wins;
+        }
+        moves.reverse();
+    }
+
+    Plan { moves, score }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{Game, Moves, Round};
+    use super::{
+        brute_force, optimise, score_multi_game, score_players, Cipher, CipherError, Constraints,
+        Game, GuideError, MismatchError, Moves, Outcome, Round, RoundReport, Rules, RulesError,
+        Standing, Symbol, Tournament,
+    };
 
     #[test]
     fn it_can_construct_a_round_from_cols() {
@@ -106,13 +776,13 @@ mod tests {
             second: Moves::Paper,
         };
 
-        let actual = Round::from_cols("A", "Z");
+        let actual = Round::from_cols("A", "Z").unwrap();
         assert_eq!(actual, expected);
     }
 
     #[test]
     fn it_should_compute_the_score() {
-        let round = Round::from_cols("A", "Y");
+        let round = Round::from_cols("A", "Y").unwrap();
         assert_eq!(round.score(), 4);
     }
 
@@ -140,7 +810,18 @@ mod tests {
         .replace('\t', "");
 
         let actual = Game::from_str(&input);
-        assert_eq!(expected, actual);
+        assert_eq!(Ok(expected), actual);
+    }
+
+    #[test]
+    fn it_reports_unknown_symbols_with_the_line_number() {
+        assert_eq!(
+            Err(GuideError {
+                line: 2,
+                error: CipherError::UnknownSymbol(String::from("Q")),
+            }),
+            Game::from_str("A Y\nB Q\nC Z")
+        );
     }
 
     #[test]
@@ -164,4 +845,368 @@ mod tests {
         let score = game.total_score();
         assert_eq!(score, 15);
     }
+
+    #[test]
+    fn it_optimises_an_unconstrained_game() {
+        let opponent = [Moves::Rock, Moves::Paper, Moves::Scissors];
+
+        let plan = optimise(&opponent, &Constraints::default());
+        assert_eq!(vec![Moves::Paper, Moves::Scissors, Moves::Rock], plan.moves);
+        assert_eq!(8 + 9 + 7, plan.score);
+    }
+
+    #[test]
+    fn it_limits_the_number_of_wins() {
+        let opponent = [Moves::Rock, Moves::Rock, Moves::Rock];
+        let constraints = Constraints {
+            max_wins: Some(1),
+            no_repeats: false,
+        };
+
+        let plan = optimise(&opponent, &constraints);
+        // One paper win (8), then the rock draws (4) outscore the scissors losses (3)
+        assert_eq!(16, plan.score);
+        assert_eq!(1, plan.moves.iter().filter(|m| **m == Moves::Paper).count());
+    }
+
+    #[test]
+    fn it_never_repeats_a_shape_when_asked() {
+        let opponent = [Moves::Rock, Moves::Rock, Moves::Rock];
+        let constraints = Constraints {
+            max_wins: None,
+            no_repeats: true,
+        };
+
+        let plan = optimise(&opponent, &constraints);
+        assert_eq!(vec![Moves::Paper, Moves::Rock, Moves::Paper], plan.moves);
+        assert_eq!(8 + 4 + 8, plan.score);
+        let total: i32 = opponent
+            .iter()
+            .zip(plan.moves.iter())
+            .map(|(first, second)| {
+                Round {
+                    first: *first,
+                    second: *second,
+                }
+                .score()
+            })
+            .sum();
+        assert_eq!(total, plan.score);
+    }
+
+    #[test]
+    fn it_optimises_an_empty_game() {
+        let plan = optimise(&[], &Constraints::default());
+        assert_eq!(0, plan.score);
+        assert!(plan.moves.is_empty());
+    }
+
+    #[test]
+    fn it_matches_the_classic_rules_with_a_three_shape_cycle() {
+        let classic = Rules::classic();
+        let cyclic = Rules::cyclic(3).unwrap();
+
+        for first in 0..3 {
+            for second in 0..3 {
+                assert_eq!(
+                    classic.outcome(first, second),
+                    cyclic.outcome(first, second)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn it_rejects_invalid_rule_tables() {
+        let names = vec!["Rock", "Paper", "Scissors"];
+        assert_eq!(
+            Err(RulesError::MissingScores {
+                shapes: 3,
+                scores: 2
+            }),
+            Rules::new(names.clone(), vec![(1, 0)], vec![1, 2])
+        );
+        assert_eq!(
+            Err(RulesError::UnknownShape(3)),
+            Rules::new(names.clone(), vec![(0, 3)], vec![1, 2, 3])
+        );
+        assert_eq!(
+            Err(RulesError::BeatsItself(1)),
+            Rules::new(names.clone(), vec![(1, 1)], vec![1, 2, 3])
+        );
+        assert_eq!(
+            Err(RulesError::MutualBeats(0, 1)),
+            Rules::new(names, vec![(1, 0), (0, 1)], vec![1, 2, 3])
+        );
+        assert_eq!(Err(RulesError::EvenCycle(4)), Rules::cyclic(4));
+    }
+
+    #[test]
+    fn it_scores_rock_paper_scissors_lizard_spock() {
+        let rules = Rules::lizard_spock();
+        let game = Game::from_rounds(vec![
+            Round {
+                first: 4,
+                second: 3,
+            },
+            Round {
+                first: 0,
+                second: 4,
+            },
+            Round {
+                first: 3,
+                second: 2,
+            },
+            Round {
+                first: 1,
+                second: 1,
+            },
+        ]);
+
+        assert_eq!(Outcome::Win, rules.outcome(4, 3));
+        assert_eq!(Outcome::Loss, rules.outcome(2, 1));
+        assert_eq!(
+            (4 + 6) + (5 + 6) + (3 + 6) + (2 + 3),
+            game.total_score_with(&rules)
+        );
+    }
+
+    #[test]
+    fn it_balances_odd_cyclic_games() {
+        let rules = Rules::cyclic(7).unwrap();
+
+        for shape in 0..rules.shapes() {
+            let wins = (0..rules.shapes())
+                .filter(|other| rules.outcome(*other, shape) == Outcome::Win)
+                .count();
+            let losses = (0..rules.shapes())
+                .filter(|other| rules.outcome(*other, shape) == Outcome::Loss)
+                .count();
+            assert_eq!((3, 3), (wins, losses));
+        }
+    }
+
+    #[test]
+    fn it_uses_custom_outcome_points() {
+        let rules = Rules::classic().with_points(10, 5, 1);
+        let round = Round {
+            first: Moves::Rock,
+            second: Moves::Scissors,
+        };
+
+        assert_eq!(3 + 1, round.score_with(&rules));
+    }
+
+    #[test]
+    fn it_rejects_unknown_symbols() {
+        assert_eq!(
+            Err(CipherError::UnknownSymbol(String::from("D"))),
+            Round::from_cols("D", "X")
+        );
+        assert_eq!(
+            Err(CipherError::UnknownSymbol(String::from("W"))),
+            Round::from_cols("A", "W")
+        );
+    }
+
+    #[test]
+    fn it_reports_the_line_of_a_bad_round() {
+        let input = "A Y\nB Q\nC Z";
+        assert_eq!(
+            Err(GuideError {
+                line: 2,
+                error: CipherError::UnknownSymbol(String::from("Q")),
+            }),
+            Game::parse(input, &Cipher::outcomes(), &Rules::classic())
+        );
+        assert_eq!(
+            Err(GuideError {
+                line: 1,
+                error: CipherError::MissingColumn,
+            }),
+            Game::parse("A\n", &Cipher::outcomes(), &Rules::classic())
+        );
+    }
+
+    #[test]
+    fn it_tolerates_extra_spaces_and_crlf_line_endings() {
+        let rules = Rules::classic();
+        let expected = Game::parse("A Y\nB X\nC Z\n", &Cipher::outcomes(), &rules).unwrap();
+
+        assert_eq!(
+            Ok(&expected),
+            Game::parse("A  Y\r\nB\tX\r\n C Z \r\n", &Cipher::outcomes(), &rules).as_ref()
+        );
+    }
+
+    #[test]
+    fn it_decodes_columns_with_a_custom_cipher() {
+        let cipher = Cipher::new(
+            vec![("R", 0), ("P", 1), ("S", 2), ("L", 3), ("K", 4)],
+            vec![
+                ("1", Symbol::Shape(3)),
+                ("2", Symbol::Shape(4)),
+                ("!", Symbol::Outcome(Outcome::Win)),
+            ],
+        );
+        let rules = Rules::lizard_spock();
+        let game = Game::parse("K 1\nR 2\nL !", &cipher, &rules).unwrap();
+
+        assert_eq!(vec![4, 0, 3], game.opponent_moves());
+        assert_eq!((4 + 6) + (5 + 6) + (1 + 6), game.total_score_with(&rules));
+    }
+
+    #[test]
+    fn it_scores_every_permutation_of_the_second_column() {
+        let input = "A Y\nB X\nC Z";
+        let results = brute_force(input, &Cipher::shapes(), &Rules::classic()).unwrap();
+
+        assert_eq!(6, results.len());
+        assert_eq!(
+            vec![
+                ("X", Symbol::Shape(0)),
+                ("Y", Symbol::Shape(1)),
+                ("Z", Symbol::Shape(2)),
+            ],
+            results[0].0.mapping()
+        );
+        assert_eq!(15, results[0].1);
+        assert_eq!(
+            "X=Rock Y=Paper Z=Scissors",
+            results[0].0.describe(&Rules::classic())
+        );
+        assert_eq!(
+            vec![15, 6, 15, 15, 15, 24],
+            results.iter().map(|(_, score)| *score).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn it_reports_each_round() {
+        let game = Game::from_str("A Y\nB X\nC Z").unwrap();
+        let report = game.report(&Rules::classic());
+
+        assert_eq!(
+            vec![
+                RoundReport {
+                    opponent: 0,
+                    player: 0,
+                    outcome: Outcome::Draw,
+                    shape_points: 1,
+                    outcome_points: 3,
+                    running_score: 4,
+                },
+                RoundReport {
+                    opponent: 1,
+                    player: 0,
+                    outcome: Outcome::Loss,
+                    shape_points: 1,
+                    outcome_points: 0,
+                    running_score: 5,
+                },
+                RoundReport {
+                    opponent: 2,
+                    player: 0,
+                    outcome: Outcome::Win,
+                    shape_points: 1,
+                    outcome_points: 6,
+                    running_score: 12,
+                },
+            ],
+            report.rounds
+        );
+        assert_eq!((1, 1, 1), (report.wins, report.draws, report.losses));
+        assert_eq!(game.total_score(), report.total);
+    }
+
+    #[test]
+    fn it_exports_the_report_as_csv() {
+        let rules = Rules::new(vec!["Rock, obviously", "Paper"], vec![(1, 0)], vec![1, 2]).unwrap();
+        let game = Game::from_rounds(vec![Round {
+            first: 0,
+            second: 1,
+        }]);
+
+        let expected = "round,opponent,player,outcome,shape_points,outcome_points,running_score\n"
+            .to_string()
+            + "1,\"Rock, obviously\",Paper,Win,2,6,8\n";
+        assert_eq!(expected, game.report(&rules).to_csv(&rules));
+    }
+
+    #[test]
+    fn it_scores_every_player_against_every_other() {
+        let rules = Rules::classic();
+
+        // Rock draws with rock and beats scissors; scissors loses to both rocks
+        assert_eq!(
+            vec![1 + 3 + 6, 1 + 3 + 6, 3],
+            score_players(&[0, 0, 2], &rules)
+        );
+        assert_eq!(vec![1 + 6, 2 + 6, 3 + 6], score_players(&[0, 1, 2], &rules));
+        assert_eq!(
+            Ok(vec![7 + 7, 8 + 7, 9 + 7]),
+            score_multi_game(&[vec![0, 1, 2], vec![0, 0, 0]], &rules)
+        );
+        assert_eq!(
+            Err(MismatchError::PlayerCount {
+                round: 2,
+                expected: 3,
+                found: 2
+            }),
+            score_multi_game(&[vec![0, 1, 2], vec![0, 0]], &rules)
+        );
+    }
+
+    #[test]
+    fn it_runs_a_round_robin_tournament() {
+        let mut tournament = Tournament::new(Rules::classic());
+        tournament.add_guide("rocky", vec![0, 0]).unwrap();
+        tournament.add_guide("papers", vec![1, 1]).unwrap();
+        tournament.add_guide("mixed", vec![2, 0]).unwrap();
+
+        let standings = tournament.standings();
+        assert_eq!(
+            vec![
+                Standing {
+                    name: String::from("papers"),
+                    wins: 3,
+                    draws: 0,
+                    losses: 1,
+                    points: 26,
+                },
+                Standing {
+                    name: String::from("mixed"),
+                    wins: 1,
+                    draws: 1,
+                    losses: 2,
+                    points: 17,
+                },
+                Standing {
+                    name: String::from("rocky"),
+                    wins: 1,
+                    draws: 1,
+                    losses: 2,
+                    points: 13,
+                },
+            ],
+            standings
+        );
+        assert!(tournament.table().starts_with("Guide"));
+    }
+
+    #[test]
+    fn it_rejects_guides_of_different_lengths() {
+        let mut tournament = Tournament::new(Rules::classic());
+        tournament.add_guide("rocky", vec![0, 0]).unwrap();
+
+        assert_eq!(
+            Err(MismatchError::GuideLength {
+                name: String::from("short"),
+                expected: 2,
+                found: 1
+            }),
+            tournament.add_guide("short", vec![1])
+        );
+        assert_eq!(1, tournament.standings().len());
+    }
 }
@@ -1,116 +1,658 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
 
 use itertools::Itertools;
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Rucksack {
-    pub first: String,
-    pub second: String,
+    pub compartments: Vec<String>,
     pub common_chars: Vec<char>,
 }
 
-fn score_char(character: &char) -> i32 {
-    let mut priorities = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ".chars();
-    (priorities.position(|c| c == *character).unwrap() as i32) + 1
+#[derive(Debug, PartialEq, Eq)]
+pub enum RucksackError {
+    UnknownItem(char),
+    DuplicateItem(char),
+    TooManyItems(usize),
+    UnevenCompartments { items: usize, compartments: usize },
+    MissingBadge,
+    AmbiguousBadge(Vec<char>),
+    IncompleteGroup(usize),
+    EmptyGroupSize,
 }
-impl Rucksack {
-    pub fn from_str(input: String) -> Rucksack {
-        let len = input.len();
-        let (first, _) = input.split_at(len - (len / 2));
-        let (_, second) = input.split_at(len / 2);
 
-        let first_set: HashSet<char> = first.chars().collect();
-        let common_chars = second
+impl fmt::Display for RucksackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RucksackError::UnknownItem(item) => write!(f, "unknown item {:?}", item),
+            RucksackError::DuplicateItem(item) => {
+                write!(f, "item {:?} appears twice in the priority table", item)
+            }
+            RucksackError::TooManyItems(count) => {
+                write!(
+                    f,
+                    "a priority table holds at most 64 items, found {}",
+                    count
+                )
+            }
+            RucksackError::UnevenCompartments {
+                items,
+                compartments,
+            } => write!(
+                f,
+                "{} items cannot be split into {} equal compartments",
+                items, compartments
+            ),
+            RucksackError::MissingBadge => write!(f, "the group shares no badge"),
+            RucksackError::AmbiguousBadge(badges) => {
+                write!(f, "the group shares more than one badge: {:?}", badges)
+            }
+            RucksackError::IncompleteGroup(lines) => {
+                write!(f, "only {} rucksacks left for the last group", lines)
+            }
+            RucksackError::EmptyGroupSize => write!(f, "groups must hold at least one rucksack"),
+        }
+    }
+}
+
+// Priorities start at 1, and an item's bit in a compartment mask is its priority - 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Priorities {
+    ascii: [u8; 128],
+    other: HashMap<char, u8>,
+    items: Vec<char>,
+}
+
+impl Default for Priorities {
+    fn default() -> Self {
+        Priorities::new("abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ").unwrap()
+    }
+}
+
+impl Priorities {
+    pub fn new(items: &str) -> Result<Priorities, RucksackError> {
+        let items: Vec<char> = items.chars().collect();
+        if items.len() > 64 {
+            return Err(RucksackError::TooManyItems(items.len()));
+        }
+
+        let mut priorities = Priorities {
+            ascii: [0; 128],
+            other: HashMap::new(),
+            items: vec![],
+        };
+        for (idx, item) in items.into_iter().enumerate() {
+            if priorities.priority(item).is_ok() {
+                return Err(RucksackError::DuplicateItem(item));
+            }
+            let priority = idx as u8 + 1;
+            if item.is_ascii() {
+                priorities.ascii[item as usize] = priority;
+            } else {
+                priorities.other.insert(item, priority);
+            }
+            priorities.items.push(item);
+        }
+        Ok(priorities)
+    }
+
+    pub fn priority(&self, item: char) -> Result<i32, RucksackError> {
+        let priority = if item.is_ascii() {
+            Some(self.ascii[item as usize]).filter(|p| *p != 0)
+        } else {
+            self.other.get(&item).copied()
+        };
+        priority
+            .map(i32::from)
+            .ok_or(RucksackError::UnknownItem(item))
+    }
+
+    pub fn mask(&self, items: &str) -> Result<u64, RucksackError> {
+        items
             .chars()
-            .filter(|c| first_set.contains(c))
-            .unique()
-            .collect_vec();
-
-        Rucksack {
-            first: String::from(first),
-            second: String::from(second),
-            common_chars,
+            .try_fold(0, |mask, item| Ok(mask | 1 << (self.priority(item)? - 1)))
+    }
+
+    pub fn items(&self, mask: u64) -> Vec<char> {
+        self.items
+            .iter()
+            .enumerate()
+            .filter(|(bit, _)| mask & (1 << bit) != 0)
+            .map(|(_, item)| *item)
+            .collect()
+    }
+}
+
+// Built once, so the default-table helpers don't rebuild it on every call
+fn default_priorities() -> &'static Priorities {
+    static DEFAULT: OnceLock<Priorities> = OnceLock::new();
+    DEFAULT.get_or_init(Priorities::default)
+}
+
+impl Rucksack {
+    pub fn from_str(input: String) -> Result<Rucksack, RucksackError> {
+        Rucksack::parse(&input, 2, default_priorities())
+    }
+
+    pub fn parse(
+        input: &str,
+        compartments: usize,
+        priorities: &Priorities,
+    ) -> Result<Rucksack, RucksackError> {
+        let items: Vec<char> = input.chars().collect();
+        if compartments == 0 || !items.len().is_multiple_of(compartments) {
+            return Err(RucksackError::UnevenCompartments {
+                items: items.len(),
+                compartments,
+            });
+        }
+
+        let size = items.len() / compartments;
+        let compartments: Vec<String> = (0..compartments)
+            .map(|c| items[c * size..(c + 1) * size].iter().collect())
+            .collect();
+        let common = compartments
+            .iter()
+            .map(|compartment| priorities.mask(compartment))
+            .try_fold(u64::MAX, |acc, mask| mask.map(|m| acc & m))?;
+
+        Ok(Rucksack {
+            compartments,
+            common_chars: priorities.items(common),
+        })
+    }
+
+    pub fn score(&self) -> Result<i32, RucksackError> {
+        self.score_with(default_priorities())
+    }
+
+    pub fn score_with(&self, priorities: &Priorities) -> Result<i32, RucksackError> {
+        self.common_chars
+            .iter()
+            .map(|c| priorities.priority(*c))
+            .sum()
+    }
+
+    fn items(&self) -> Vec<Vec<char>> {
+        self.compartments
+            .iter()
+            .map(|compartment| compartment.chars().collect())
+            .collect()
+    }
+
+    // An item stops being common once one compartment holds no copies, so every copy
+    // in the compartment holding the fewest moves to the one holding the most
+    pub fn rebalance(&self, priorities: &Priorities) -> Result<Rebalance, RucksackError> {
+        let compartments = self.items();
+        let mut moves = vec![];
+        let mut priority_reduction = 0;
+        for item in self.common_chars.iter() {
+            let (from, to) = (sparsest(&compartments, *item), keeper(&compartments, *item));
+            if from == to {
+                continue;
+            }
+            moves.push(Move {
+                item: *item,
+                from,
+                to,
+                count: compartments[from].iter().filter(|c| *c == item).count(),
+            });
+            priority_reduction += priorities.priority(*item)?;
+        }
+
+        Ok(Rebalance {
+            moves,
+            priority_reduction,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Move {
+    pub item: char,
+    pub from: usize,
+    pub to: usize,
+    pub count: usize,
+}
+
+// Moves leave compartment sizes unbalanced; use `suggest_swaps` to keep them equal
+#[derive(Debug, PartialEq, Eq)]
+pub struct Rebalance {
+    pub moves: Vec<Move>,
+    pub priority_reduction: i32,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Swap {
+    pub rucksack: usize,
+    pub compartment: usize,
+    pub item: char,
+    pub other_rucksack: usize,
+    pub other_compartment: usize,
+    pub other_item: char,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct SwapPlan {
+    pub swaps: Vec<Swap>,
+    pub priority_reduction: i32,
+    pub unresolved: Vec<(usize, char)>,
+}
+
+// The compartment holding the most copies of an item, which keeps them all
+fn keeper(compartments: &[Vec<char>], item: char) -> usize {
+    let counts = compartments
+        .iter()
+        .map(|compartment| compartment.iter().filter(|c| **c == item).count())
+        .collect::<Vec<_>>();
+    let most = counts.iter().max().copied().unwrap_or(0);
+    counts.iter().position(|count| *count == most).unwrap_or(0)
+}
+
+// The compartment holding the fewest copies of an item, preferring the last on ties
+fn sparsest(compartments: &[Vec<char>], item: char) -> usize {
+    (0..compartments.len())
+        .rev()
+        .min_by_key(|idx| compartments[*idx].iter().filter(|c| **c == item).count())
+        .unwrap_or(0)
+}
+
+// Items found in every compartment, matching `Rucksack::common_chars`
+fn common_items(compartments: &[Vec<char>]) -> Vec<char> {
+    let Some((first, rest)) = compartments.split_first() else {
+        return vec![];
+    };
+    first
+        .iter()
+        .unique()
+        .filter(|item| rest.iter().all(|compartment| compartment.contains(item)))
+        .copied()
+        .sorted()
+        .collect()
+}
+
+// Whether placing the item in `except` would leave it in every compartment
+fn held_by_others(compartments: &[Vec<char>], except: usize, item: char) -> bool {
+    compartments
+        .iter()
+        .enumerate()
+        .all(|(idx, compartment)| idx == except || compartment.contains(&item))
+}
+
+fn find_partner(
+    state: &[Vec<Vec<char>>],
+    rucksack: usize,
+    compartment: usize,
+    item: char,
+) -> Option<(usize, usize, char)> {
+    let mut fallback = None;
+    for (other, compartments) in state.iter().enumerate() {
+        if other == rucksack {
+            continue;
+        }
+        for (other_compartment, items) in compartments.iter().enumerate() {
+            if held_by_others(compartments, other_compartment, item) {
+                continue;
+            }
+            for other_item in items.iter().unique() {
+                if *other_item == item || held_by_others(&state[rucksack], compartment, *other_item)
+                {
+                    continue;
+                }
+                let candidate = (other, other_compartment, *other_item);
+                // Prefer handing over a common item, which fixes both rucksacks at once
+                if held_by_others(compartments, other_compartment, *other_item)
+                    && sparsest(compartments, *other_item) == other_compartment
+                {
+                    return Some(candidate);
+                }
+                fallback = fallback.or(Some(candidate));
+            }
+        }
+    }
+    fallback
+}
+
+pub fn suggest_swaps(
+    sacks: &[Rucksack],
+    priorities: &Priorities,
+) -> Result<SwapPlan, RucksackError> {
+    let mut before = 0;
+    for sack in sacks {
+        before += sack.score_with(priorities)?;
+    }
+
+    let mut state: Vec<Vec<Vec<char>>> = sacks.iter().map(Rucksack::items).collect();
+    let mut swaps = vec![];
+    let mut unresolved = vec![];
+    for rucksack in 0..state.len() {
+        for item in common_items(&state[rucksack]) {
+            while common_items(&state[rucksack]).contains(&item) {
+                let compartment = sparsest(&state[rucksack], item);
+                let Some((other, other_compartment, other_item)) =
+                    find_partner(&state, rucksack, compartment, item)
+                else {
+                    unresolved.push((rucksack, item));
+                    break;
+                };
+
+                let from = &mut state[rucksack][compartment];
+                let idx = from.iter().position(|c| *c == item).unwrap();
+                from[idx] = other_item;
+                let to = &mut state[other][other_compartment];
+                let idx = to.iter().position(|c| *c == other_item).unwrap();
+                to[idx] = item;
+
+                swaps.push(Swap {
+                    rucksack,
+                    compartment,
+                    item,
+                    other_rucksack: other,
+                    other_compartment,
+                    other_item,
+                });
+            }
         }
     }
 
-    pub fn score(&self) -> i32 {
-        self.common_chars.iter().map(score_char).sum()
+    let mut after = 0;
+    for compartments in state.iter() {
+        for item in common_items(compartments) {
+            after += priorities.priority(item)?;
+        }
     }
+
+    Ok(SwapPlan {
+        swaps,
+        priority_reduction: before - after,
+        unresolved,
+    })
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct ElfGroup {
-    rucksacks: [Rucksack; 3],
+    rucksacks: Vec<Rucksack>,
     badge: char,
     score: i32,
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub struct GroupError {
+    pub group: usize,
+    pub error: RucksackError,
+}
+
+impl fmt::Display for GroupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "group {}: {}", self.group, self.error)
+    }
+}
+
 impl ElfGroup {
-    pub fn from_lines(input: [String; 3]) -> ElfGroup {
-        let badge = String::from(&input[2])
-            .chars()
-            .find(|c| input[0].contains(*c) && input[1].contains(*c))
-            .unwrap();
-        let rucksacks = input.map(Rucksack::from_str);
-        let score = score_char(&badge);
+    pub fn from_lines(input: [String; 3]) -> Result<ElfGroup, RucksackError> {
+        ElfGroup::parse(&input, 2, default_priorities())
+    }
+
+    pub fn parse(
+        input: &[String],
+        compartments: usize,
+        priorities: &Priorities,
+    ) -> Result<ElfGroup, RucksackError> {
+        let common = input
+            .iter()
+            .map(|line| priorities.mask(line))
+            .try_fold(u64::MAX, |acc, mask| mask.map(|m| acc & m))?;
+        let badge = match priorities.items(common)[..] {
+            [badge] => badge,
+            [] => return Err(RucksackError::MissingBadge),
+            ref badges => return Err(RucksackError::AmbiguousBadge(badges.to_vec())),
+        };
+        let rucksacks = input
+            .iter()
+            .map(|line| Rucksack::parse(line, compartments, priorities))
+            .collect::<Result<Vec<_>, _>>()?;
+        let score = priorities.priority(badge)?;
 
-        ElfGroup {
+        Ok(ElfGroup {
             rucksacks,
             badge,
             score,
-        }
+        })
     }
 }
 
-pub fn score_lines(inputs: Vec<String>) -> i32 {
+pub fn score_lines(inputs: Vec<String>) -> Result<i32, RucksackError> {
+    score_lines_with(&inputs, 2, default_priorities())
+}
+
+pub fn score_lines_with(
+    inputs: &[String],
+    compartments: usize,
+    priorities: &Priorities,
+) -> Result<i32, RucksackError> {
     inputs
         .iter()
-        .map(|line| Rucksack::from_str(String::from(line)).score())
+        .map(|line| Rucksack::parse(line, compartments, priorities)?.score_with(priorities))
         .sum()
 }
 
-pub fn group_and_score(inputs: Vec<String>) -> i32 {
+pub fn group_and_score(inputs: Vec<String>) -> Result<i32, GroupError> {
+    group_and_score_with(&inputs, 3, 2, default_priorities())
+}
+
+pub fn group_and_score_with(
+    inputs: &[String],
+    group_size: usize,
+    compartments: usize,
+    priorities: &Priorities,
+) -> Result<i32, GroupError> {
+    if group_size == 0 {
+        return Err(GroupError {
+            group: 1,
+            error: RucksackError::EmptyGroupSize,
+        });
+    }
     inputs
-        .chunks_exact(3)
-        .map(|chunk| {
-            ElfGroup::from_lines([
-                String::from(&chunk[0]),
-                String::from(&chunk[1]),
-                String::from(&chunk[2]),
-            ])
-            .score
+        .chunks(group_size)
+        .enumerate()
+        .map(|(idx, chunk)| {
+            let group = if chunk.len() < group_size {
+                Err(RucksackError::IncompleteGroup(chunk.len()))
+            } else {
+                ElfGroup::parse(chunk, compartments, priorities)
+            };
+            group.map(|group| group.score).map_err(|error| GroupError {
+                group: idx + 1,
+                error,
+            })
         })
         .sum()
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Objective {
+    Maximise,
+    Minimise,
+}
+
+impl Objective {
+    fn better(&self, candidate: i32, best: i32) -> bool {
+        match self {
+            Objective::Maximise => candidate > best,
+            Objective::Minimise => candidate < best,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Regrouping {
+    pub groups: Vec<[usize; 3]>,
+    pub badges: Vec<char>,
+    pub score: i32,
+}
+
+// Which rucksacks are already grouped, one bit each, so any number can be regrouped
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Used(Vec<u64>);
+
+impl Used {
+    fn new(count: usize) -> Used {
+        Used(vec![0; count.div_ceil(64)])
+    }
+
+    fn contains(&self, idx: usize) -> bool {
+        self.0[idx / 64] & 1 << (idx % 64) != 0
+    }
+
+    fn with(&self, group: [usize; 3]) -> Used {
+        let mut next = self.clone();
+        for idx in group {
+            next.0[idx / 64] |= 1 << (idx % 64);
+        }
+        next
+    }
+
+    fn first_free(&self, count: usize) -> Option<usize> {
+        self.0
+            .iter()
+            .enumerate()
+            .find(|(_, word)| **word != u64::MAX)
+            .map(|(idx, word)| idx * 64 + word.trailing_ones() as usize)
+            .filter(|idx| *idx < count)
+    }
+}
+
+// Best score for the rucksacks not yet in `used`, with the pair grouped alongside the first of them
+type Choice = Option<(i32, usize, usize)>;
+
+struct Regrouper {
+    count: usize,
+    // For each rucksack, the later rucksacks it can form a group with and that group's score
+    partners: Vec<Vec<(usize, usize, i32)>>,
+    objective: Objective,
+    memo: HashMap<Used, Choice>,
+}
+
+impl Regrouper {
+    fn solve(&mut self, used: &Used) -> Choice {
+        let Some(first) = used.first_free(self.count) else {
+            return Some((0, 0, 0));
+        };
+        if let Some(choice) = self.memo.get(used) {
+            return *choice;
+        }
+
+        let mut best: Choice = None;
+        for idx in 0..self.partners[first].len() {
+            let (second, third, score) = self.partners[first][idx];
+            if used.contains(second) || used.contains(third) {
+                continue;
+            }
+            if let Some((rest, _, _)) = self.solve(&used.with([first, second, third])) {
+                let candidate = score + rest;
+                if best.is_none_or(|(b, _, _)| self.objective.better(candidate, b)) {
+                    best = Some((candidate, second, third));
+                }
+            }
+        }
+
+        self.memo.insert(used.clone(), best);
+        best
+    }
+}
+
+// Exact search, so inputs where many triples share interchangeable badges can take exponential time
+pub fn regroup(
+    inputs: &[String],
+    objective: Objective,
+    priorities: &Priorities,
+) -> Result<Option<Regrouping>, RucksackError> {
+    let count = inputs.len();
+    let masks = inputs
+        .iter()
+        .map(|line| priorities.mask(line))
+        .collect::<Result<Vec<_>, _>>()?;
+    if !count.is_multiple_of(3) {
+        return Ok(None);
+    }
+
+    // The same single-badge rule as `ElfGroup::parse`, intersecting the masks directly
+    // so that hundreds of rucksacks don't re-parse every triple
+    let mut badges = HashMap::new();
+    let mut partners = vec![vec![]; count];
+    for (a, b) in (0..count).tuple_combinations() {
+        let pair = masks[a] & masks[b];
+        if pair == 0 {
+            continue;
+        }
+        for (c, mask) in masks.iter().enumerate().skip(b + 1) {
+            let common = pair & mask;
+            if common.count_ones() == 1 {
+                let badge = priorities.items(common)[0];
+                badges.insert((a, b, c), badge);
+                partners[a].push((b, c, priorities.priority(badge)?));
+            }
+        }
+    }
+
+    let mut regrouper = Regrouper {
+        count,
+        partners,
+        objective,
+        memo: HashMap::new(),
+    };
+    let Some((score, _, _)) = regrouper.solve(&Used::new(count)) else {
+        return Ok(None);
+    };
+
+    let mut regrouping = Regrouping {
+        groups: vec![],
+        badges: vec![],
+        score,
+    };
+    let mut used = Used::new(count);
+    while let Some(first) = used.first_free(count) {
+        let (_, second, third) = regrouper.memo[&used].unwrap();
+        regrouping.groups.push([first, second, third]);
+        regrouping.badges.push(badges[&(first, second, third)]);
+        used = used.with([first, second, third]);
+    }
+    Ok(Some(regrouping))
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{ElfGroup, Rucksack};
+    use super::{
+        group_and_score_with, regroup, score_lines_with, suggest_swaps, ElfGroup, GroupError, Move,
+        Objective, Priorities, Rucksack, RucksackError, Swap,
+    };
 
     #[test]
     fn it_splits_items_into_compartments() {
         let input = String::from("vJrwpWtwJgWrhcsFMMfFFhFp");
 
         let expected = Rucksack {
-            first: String::from("vJrwpWtwJgWr"),
-            second: String::from("hcsFMMfFFhFp"),
+            compartments: vec![String::from("vJrwpWtwJgWr"), String::from("hcsFMMfFFhFp")],
             common_chars: vec!['p'],
         };
 
         let actual = Rucksack::from_str(input);
-        assert_eq!(expected, actual);
+        assert_eq!(Ok(expected), actual);
     }
 
     #[test]
     fn it_splits_an_uneven_list_of_items() {
         let input = String::from("jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL");
         let expected = Rucksack {
-            first: String::from("jqHRNqRjqzjGDLGL"),
-            second: String::from("rsFMfFZSrLrFZsSL"),
+            compartments: vec![
+                String::from("jqHRNqRjqzjGDLGL"),
+                String::from("rsFMfFZSrLrFZsSL"),
+            ],
             common_chars: vec!['L'],
         };
         let actual = Rucksack::from_str(input);
-        assert_eq!(expected, actual);
+        assert_eq!(Ok(expected), actual);
     }
 
     #[test]
@@ -126,13 +668,22 @@ mod tests {
 
         let expectations = [16, 38, 42, 22, 20, 19];
         let results = inputs.map(|i| {
-            let sack = Rucksack::from_str(i);
-            sack.score()
+            let sack = Rucksack::from_str(i).unwrap();
+            sack.score().unwrap()
         });
 
         assert_eq!(expectations, results);
     }
 
+    #[test]
+    fn it_reports_items_missing_from_the_default_table() {
+        let digits = Priorities::new("0123456789").unwrap();
+        let sack = Rucksack::parse("1212", 2, &digits).unwrap();
+
+        assert_eq!(Ok(2 + 3), sack.score_with(&digits));
+        assert_eq!(Err(RucksackError::UnknownItem('1')), sack.score());
+    }
+
     #[test]
     fn it_calculates_total_line_score() {
         let inputs = vec![
@@ -146,7 +697,7 @@ mod tests {
 
         let expected = 157;
         let result = super::score_lines(inputs);
-        assert_eq!(expected, result);
+        assert_eq!(Ok(expected), result);
     }
 
     #[test]
@@ -158,20 +709,20 @@ mod tests {
         ];
 
         let expected = ElfGroup {
-            rucksacks: [
+            rucksacks: vec![
                 Rucksack {
-                    first: String::from("vJrwpWtwJgWr"),
-                    second: String::from("hcsFMMfFFhFp"),
+                    compartments: vec![String::from("vJrwpWtwJgWr"), String::from("hcsFMMfFFhFp")],
                     common_chars: vec!['p'],
                 },
                 Rucksack {
-                    first: String::from("jqHRNqRjqzjGDLGL"),
-                    second: String::from("rsFMfFZSrLrFZsSL"),
+                    compartments: vec![
+                        String::from("jqHRNqRjqzjGDLGL"),
+                        String::from("rsFMfFZSrLrFZsSL"),
+                    ],
                     common_chars: vec!['L'],
                 },
                 Rucksack {
-                    first: String::from("PmmdzqPrV"),
-                    second: String::from("vPwwTWBwg"),
+                    compartments: vec![String::from("PmmdzqPrV"), String::from("vPwwTWBwg")],
                     common_chars: vec!['P'],
                 },
             ],
@@ -180,7 +731,7 @@ mod tests {
         };
 
         let actual = ElfGroup::from_lines(input);
-        assert_eq!(expected, actual);
+        assert_eq!(Ok(expected), actual);
     }
 
     #[test]
@@ -195,6 +746,319 @@ mod tests {
         ];
         let expected = 70;
         let actual = super::group_and_score(inputs);
-        assert_eq!(expected, actual);
+        assert_eq!(Ok(expected), actual);
+    }
+
+    #[test]
+    fn it_reports_lines_that_cannot_be_split_evenly() {
+        assert_eq!(
+            Err(RucksackError::UnevenCompartments {
+                items: 3,
+                compartments: 2,
+            }),
+            Rucksack::from_str(String::from("abc"))
+        );
+        assert_eq!(
+            Err(RucksackError::UnevenCompartments {
+                items: 3,
+                compartments: 2,
+            }),
+            super::score_lines(vec![String::from("abc")])
+        );
+    }
+
+    #[test]
+    fn it_looks_up_priorities_in_the_default_table() {
+        let priorities = Priorities::default();
+
+        assert_eq!(Ok(1), priorities.priority('a'));
+        assert_eq!(Ok(27), priorities.priority('A'));
+        assert_eq!(Ok(52), priorities.priority('Z'));
+        assert_eq!(
+            Err(RucksackError::UnknownItem('1')),
+            priorities.priority('1')
+        );
+    }
+
+    #[test]
+    fn it_builds_a_custom_priority_table() {
+        let priorities = Priorities::new("0123456789éü").unwrap();
+
+        assert_eq!(Ok(1), priorities.priority('0'));
+        assert_eq!(Ok(12), priorities.priority('ü'));
+        assert_eq!(
+            Err(RucksackError::DuplicateItem('1')),
+            Priorities::new("011")
+        );
+        assert_eq!(
+            Err(RucksackError::TooManyItems(65)),
+            Priorities::new(&"a".repeat(65))
+        );
+    }
+
+    #[test]
+    fn it_parses_rucksacks_with_a_custom_table() {
+        let priorities = Priorities::new("0123456789éü").unwrap();
+        let sack = Rucksack::parse("ü2é9éü", 2, &priorities).unwrap();
+
+        assert_eq!("ü2é", sack.compartments[0]);
+        assert_eq!(vec!['é', 'ü'], sack.common_chars);
+        assert_eq!(Ok(23), sack.score_with(&priorities));
+        assert_eq!(
+            Err(RucksackError::UnknownItem('x')),
+            Rucksack::parse("12x3", 2, &priorities)
+        );
+    }
+
+    #[test]
+    fn it_splits_items_into_any_number_of_compartments() {
+        let priorities = Priorities::default();
+        let sack = Rucksack::parse("abcxbcyzc", 3, &priorities).unwrap();
+
+        assert_eq!(
+            vec![
+                String::from("abc"),
+                String::from("xbc"),
+                String::from("yzc"),
+            ],
+            sack.compartments
+        );
+        assert_eq!(vec!['c'], sack.common_chars);
+        assert_eq!(
+            Err(RucksackError::UnevenCompartments {
+                items: 9,
+                compartments: 2,
+            }),
+            Rucksack::parse("abcxbcyzc", 2, &priorities)
+        );
+        assert_eq!(
+            Ok(3 + 6 + 1),
+            score_lines_with(
+                &[String::from("abcxbcyzc"), String::from("fafafa")],
+                3,
+                &priorities
+            )
+        );
+    }
+
+    #[test]
+    fn it_groups_any_number_of_elves() {
+        let inputs = vec![
+            String::from("abcd"),
+            String::from("dxef"),
+            String::from("wxyz"),
+            String::from("zqzq"),
+        ];
+
+        assert_eq!(
+            Ok(4 + 26),
+            group_and_score_with(&inputs, 2, 2, &Priorities::default())
+        );
+    }
+
+    #[test]
+    fn it_reports_badge_problems_instead_of_panicking() {
+        let priorities = Priorities::default();
+        let missing = vec![String::from("abcd"), String::from("efgh")];
+        let ambiguous = vec![String::from("abcd"), String::from("abef")];
+        let leftover = vec![
+            String::from("abcd"),
+            String::from("aefg"),
+            String::from("hijk"),
+        ];
+
+        assert_eq!(
+            Err(GroupError {
+                group: 1,
+                error: RucksackError::MissingBadge,
+            }),
+            group_and_score_with(&missing, 2, 2, &priorities)
+        );
+        assert_eq!(
+            Err(GroupError {
+                group: 1,
+                error: RucksackError::AmbiguousBadge(vec!['a', 'b']),
+            }),
+            group_and_score_with(&ambiguous, 2, 2, &priorities)
+        );
+        assert_eq!(
+            Err(GroupError {
+                group: 2,
+                error: RucksackError::IncompleteGroup(1),
+            }),
+            group_and_score_with(&leftover, 2, 2, &priorities)
+        );
+        assert_eq!(
+            Err(GroupError {
+                group: 1,
+                error: RucksackError::EmptyGroupSize,
+            }),
+            group_and_score_with(&[String::from("abca")], 0, 2, &priorities)
+        );
+    }
+
+    #[test]
+    fn it_regroups_rucksacks_for_the_best_badge_score() {
+        let inputs = vec![
+            String::from("aZ"),
+            String::from("bY"),
+            String::from("aY"),
+            String::from("Zb"),
+            String::from("aZc"),
+            String::from("bYc"),
+        ];
+        let priorities = Priorities::default();
+
+        let max = regroup(&inputs, Objective::Maximise, &priorities)
+            .unwrap()
+            .unwrap();
+        assert_eq!(vec![[0, 3, 4], [1, 2, 5]], max.groups);
+        assert_eq!(vec!['Z', 'Y'], max.badges);
+        assert_eq!(52 + 51, max.score);
+
+        let min = regroup(&inputs, Objective::Minimise, &priorities)
+            .unwrap()
+            .unwrap();
+        assert_eq!(vec![[0, 2, 4], [1, 3, 5]], min.groups);
+        assert_eq!(vec!['a', 'b'], min.badges);
+        assert_eq!(1 + 2, min.score);
+    }
+
+    #[test]
+    fn it_regroups_more_than_sixty_four_rucksacks() {
+        let items = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+        let inputs = (0..75)
+            .map(|idx| items.chars().nth(idx / 3).unwrap().to_string())
+            .collect::<Vec<_>>();
+
+        let regrouping = regroup(&inputs, Objective::Maximise, &Priorities::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(25, regrouping.groups.len());
+        assert_eq!([72, 73, 74], regrouping.groups[24]);
+        assert_eq!((1..=25).sum::<i32>(), regrouping.score);
+    }
+
+    #[test]
+    fn it_reports_when_no_regrouping_exists() {
+        let priorities = Priorities::default();
+        let unmatched = vec![String::from("ab"), String::from("cd"), String::from("ef")];
+        let uneven = vec![String::from("ab"), String::from("ab")];
+
+        assert_eq!(
+            Ok(None),
+            regroup(&unmatched, Objective::Maximise, &priorities)
+        );
+        assert_eq!(Ok(None), regroup(&uneven, Objective::Maximise, &priorities));
+        assert_eq!(
+            Err(RucksackError::UnknownItem('1')),
+            regroup(&[String::from("a1")], Objective::Maximise, &priorities)
+        );
+    }
+
+    #[test]
+    fn it_suggests_moves_to_separate_compartments() {
+        let sack = Rucksack::from_str(String::from("aabcadbe")).unwrap();
+        let rebalance = sack.rebalance(&Priorities::default()).unwrap();
+
+        assert_eq!(
+            vec![
+                Move {
+                    item: 'a',
+                    from: 1,
+                    to: 0,
+                    count: 1,
+                },
+                Move {
+                    item: 'b',
+                    from: 1,
+                    to: 0,
+                    count: 1,
+                },
+            ],
+            rebalance.moves
+        );
+        assert_eq!(1 + 2, rebalance.priority_reduction);
+    }
+
+    #[test]
+    fn it_suggests_swaps_between_rucksacks() {
+        let sacks = vec![
+            Rucksack::from_str(String::from("abcdaxyz")).unwrap(),
+            Rucksack::from_str(String::from("efghijkl")).unwrap(),
+        ];
+        let plan = suggest_swaps(&sacks, &Priorities::default()).unwrap();
+
+        assert_eq!(
+            vec![Swap {
+                rucksack: 0,
+                compartment: 1,
+                item: 'a',
+                other_rucksack: 1,
+                other_compartment: 0,
+                other_item: 'e',
+            }],
+            plan.swaps
+        );
+        assert_eq!(1, plan.priority_reduction);
+        assert!(plan.unresolved.is_empty());
+    }
+
+    #[test]
+    fn it_prefers_swaps_that_fix_both_rucksacks() {
+        let sacks = vec![
+            Rucksack::from_str(String::from("abcdaxyz")).unwrap(),
+            Rucksack::from_str(String::from("efgzpqzr")).unwrap(),
+        ];
+        let plan = suggest_swaps(&sacks, &Priorities::default()).unwrap();
+
+        assert_eq!(1, plan.swaps.len());
+        assert_eq!('z', plan.swaps[0].other_item);
+        assert_eq!(1 + 26, plan.priority_reduction);
+    }
+
+    #[test]
+    fn it_rebalances_rucksacks_with_more_compartments() {
+        let priorities = Priorities::default();
+        let partly_shared = Rucksack::parse("abcadexyz", 3, &priorities).unwrap();
+        assert!(partly_shared
+            .rebalance(&priorities)
+            .unwrap()
+            .moves
+            .is_empty());
+
+        let plan = suggest_swaps(&[partly_shared], &priorities).unwrap();
+        assert!(plan.swaps.is_empty());
+        assert!(plan.unresolved.is_empty());
+        assert_eq!(0, plan.priority_reduction);
+
+        let shared = Rucksack::parse("abcadeafg", 3, &priorities).unwrap();
+        let rebalance = shared.rebalance(&priorities).unwrap();
+        assert_eq!(
+            vec![Move {
+                item: 'a',
+                from: 2,
+                to: 0,
+                count: 1,
+            }],
+            rebalance.moves
+        );
+        assert_eq!(1, rebalance.priority_reduction);
+
+        let other = Rucksack::parse("hijklmnop", 3, &priorities).unwrap();
+        let plan = suggest_swaps(&[shared, other], &priorities).unwrap();
+        assert_eq!(
+            vec![Swap {
+                rucksack: 0,
+                compartment: 2,
+                item: 'a',
+                other_rucksack: 1,
+                other_compartment: 0,
+                other_item: 'h',
+            }],
+            plan.swaps
+        );
+        assert_eq!(1, plan.priority_reduction);
+        assert!(plan.unresolved.is_empty());
     }
 }
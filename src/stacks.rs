@@ -1,102 +1,397 @@
 use regex::Regex;
-use std::collections::VecDeque;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    num::NonZeroUsize,
+};
 
 use itertools::Itertools;
 
 #[derive(Debug, PartialEq, Eq)]
 struct Operation {
+    line: usize,
     quantity: usize,
     source: usize,
     target: usize,
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub struct Crane {
-    pub stacks: Vec<String>,
-    operations: VecDeque<Operation>,
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Model {
+    CrateMover9000,
+    CrateMover9001,
+    Capacity(NonZeroUsize),
 }
 
-trait Take<T> {
-    fn take(&mut self, n: usize) -> Option<T>;
+impl Model {
+    // A crane that can't lift anything isn't a model
+    pub fn capacity(capacity: usize) -> Option<Model> {
+        NonZeroUsize::new(capacity).map(Model::Capacity)
+    }
+
+    // How many crates a single lift can carry
+    fn lift(&self, quantity: usize) -> usize {
+        match self {
+            Model::CrateMover9000 => 1,
+            Model::CrateMover9001 => quantity.max(1),
+            Model::Capacity(capacity) => capacity.get(),
+        }
+    }
 }
 
-impl Take<String> for String {
-    fn take(&mut self, n: usize) -> Option<String> {
-        if n <= self.len() {
-            let split = String::from(&self[..n]);
-            self.replace_range(..n, "");
-            return Some(split);
+impl fmt::Display for Model {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Model::CrateMover9000 => write!(f, "CrateMover 9000"),
+            Model::CrateMover9001 => write!(f, "CrateMover 9001"),
+            Model::Capacity(capacity) => write!(f, "Crane lifting {} at a time", capacity),
         }
-        None
     }
 }
 
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "move {} from {} to {}",
+            self.quantity, self.source, self.target
+        )
+    }
+}
+
+// Twice the character position of a match's midpoint, to keep it an integer
+fn centre(line: &str, m: &regex::Match) -> usize {
+    let start = line[..m.start()].chars().count();
+    2 * start + m.as_str().chars().count() - 1
+}
+
+type Layout = Vec<Vec<String>>;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Crane {
+    pub stacks: Vec<Vec<String>>,
+    operations: VecDeque<Operation>,
+}
+
 impl Crane {
-    fn parse_stacks(stack_input: Vec<&str>) -> Vec<String> {
-        let len: usize = stack_input[0].len();
-        let size = len / 4 + (len % 4 != 0) as usize;
-        (0..size)
-            .map(|i| {
-                stack_input
+    // Each stack is bottom first, so the top crate is always at the end. Columns come
+    // from the numbered footer, so trimmed lines and `[AB]` labels still line up
+    fn parse_stacks(drawing: Vec<&str>) -> Result<Vec<Vec<String>>, ParseError> {
+        let crate_re = Regex::new(r"\[([^\]]+)\]").unwrap();
+        let number_re = Regex::new(r"\d+").unwrap();
+        let Some((footer, rows)) = drawing.split_last() else {
+            return Ok(vec![]);
+        };
+        let columns = number_re
+            .find_iter(footer)
+            .map(|m| centre(footer, &m))
+            .collect_vec();
+        if columns.is_empty() {
+            return Err(ParseError {
+                line: drawing.len(),
+                content: footer.to_string(),
+            });
+        }
+
+        let mut stacks = vec![vec![]; columns.len()];
+        for row in rows.iter().rev() {
+            for label in crate_re.captures_iter(row) {
+                let position = centre(row, &label.get(0).unwrap());
+                let (column, _) = columns
                     .iter()
-                    .map(|line| line.chars().nth(i * 4 + 1).unwrap())
-                    .collect::<String>()
-                    .replace(' ', "")
-            })
-            .collect_vec()
+                    .enumerate()
+                    .min_by_key(|(_, c)| c.abs_diff(position))
+                    .unwrap();
+                stacks[column].push(label[1].trim().to_string());
+            }
+        }
+        Ok(stacks)
     }
 
-    fn parse_operations(ops_input: Vec<&str>) -> VecDeque<Operation> {
+    fn parse_operations(
+        ops_input: Vec<&str>,
+        first_line: usize,
+    ) -> Result<VecDeque<Operation>, ParseError> {
         let re = Regex::new(r"^move (\d+) from (\d+) to (\d+)$").unwrap();
 
-        let ops = ops_input
-            .iter()
-            .filter(|l| !l.is_empty())
-            .map(|c| re.captures(c).unwrap())
-            .map(|c| Operation {
-                quantity: c[1].parse().unwrap(),
-                source: c[2].parse().unwrap(),
-                target: c[3].parse().unwrap(),
-            })
-            .collect_vec();
-        VecDeque::from(ops)
+        let mut ops = VecDeque::new();
+        for (idx, content) in ops_input.iter().enumerate() {
+            if content.trim().is_empty() {
+                continue;
+            }
+            let line = idx + first_line;
+            let malformed = || ParseError {
+                line,
+                content: content.to_string(),
+            };
+            let c = re.captures(content.trim()).ok_or_else(malformed)?;
+            ops.push_back(Operation {
+                line,
+                quantity: c[1].parse().map_err(|_| malformed())?,
+                source: c[2].parse().map_err(|_| malformed())?,
+                target: c[3].parse().map_err(|_| malformed())?,
+            });
+        }
+        Ok(ops)
     }
 
-    pub fn from_str(input: &str) -> Crane {
-        let lines: Vec<&str> = input.split('\n').collect();
-        let (split_index, _) = lines.iter().find_position(|l| l.is_empty()).unwrap();
+    pub fn from_str(input: &str) -> Result<Crane, ParseError> {
+        let lines: Vec<&str> = input.lines().collect();
+        let split_index = lines
+            .iter()
+            .position(|l| l.trim().is_empty())
+            .unwrap_or(lines.len());
 
-        Crane {
-            stacks: Crane::parse_stacks(lines[0..split_index - 1].to_vec()),
-            operations: Crane::parse_operations(lines[split_index + 1..].to_vec()),
-        }
+        Ok(Crane {
+            stacks: Crane::parse_stacks(lines[..split_index].to_vec())?,
+            operations: Crane::parse_operations(lines[split_index..].to_vec(), split_index + 1)?,
+        })
     }
 
     fn operate(&mut self) {
+        self.operate_with(Model::CrateMover9000)
+    }
+
+    fn operate_with(&mut self, model: Model) {
         let operation = self.operations.pop_front().unwrap();
-        let split: String = self.stacks[operation.source - 1]
-            .take(operation.quantity)
-            .unwrap()
-            .chars()
-            .rev()
-            .collect();
-        self.stacks[operation.target - 1].insert_str(0, &split);
+        apply(&mut self.stacks, &operation, model);
     }
 
-    pub fn operate_all(&mut self) -> String {
-        (0..self.operations.len()).for_each(|_| self.operate());
+    fn top(&self) -> String {
         self.stacks
             .iter()
-            .map(|s| s.chars().next().unwrap())
+            .map(|s| s.last().unwrap().as_str())
             .collect()
     }
+
+    pub fn operate_all(&mut self) -> String {
+        self.operate_all_with(Model::CrateMover9000)
+    }
+
+    pub fn operate_all_with(&mut self, model: Model) -> String {
+        self.operate_each(model, |_| ())
+    }
+
+    pub fn operate_each<F: FnMut(&Crane)>(&mut self, model: Model, mut after: F) -> String {
+        while !self.operations.is_empty() {
+            self.operate_with(model);
+            after(self);
+        }
+        self.top()
+    }
+
+    pub fn operate_all_verbose(&mut self, model: Model) -> String {
+        self.operate_each(model, |crane| println!("{}\n", crane))
+    }
+
+    // Walks every operation on a copy of the stacks, skipping the ones that can't be performed
+    pub fn validate_with(&self, model: Model) -> Validation {
+        let mut stacks = self.stacks.clone();
+        let mut max_heights = stacks.iter().map(Vec::len).collect_vec();
+        let mut errors = vec![];
+
+        for operation in self.operations.iter() {
+            let error = [operation.source, operation.target]
+                .into_iter()
+                .find(|stack| *stack == 0 || *stack > stacks.len())
+                .map(MoveError::NoSuchStack)
+                .or_else(|| {
+                    let available = stacks[operation.source - 1].len();
+                    (available < operation.quantity).then_some(MoveError::NotEnoughCrates {
+                        stack: operation.source,
+                        available,
+                        requested: operation.quantity,
+                    })
+                });
+
+            match error {
+                Some(error) => errors.push(InvalidMove {
+                    line: operation.line,
+                    error,
+                }),
+                None => {
+                    apply(&mut stacks, operation, model);
+                    let height = stacks[operation.target - 1].len();
+                    let max = &mut max_heights[operation.target - 1];
+                    *max = height.max(*max);
+                }
+            }
+        }
+
+        Validation {
+            errors,
+            max_heights,
+            layout: Crane {
+                stacks,
+                operations: VecDeque::new(),
+            },
+        }
+    }
+
+    pub fn validate(&self) -> Validation {
+        self.validate_with(Model::CrateMover9000)
+    }
+
+    // Breadth-first search over layouts, so the first plan found uses the fewest operations.
+    // Returns the plan in the same `move N from A to B` format that `from_str` reads, or
+    // `None` when the layouts hold different crates or the target can't be reached. The
+    // search has no bound on the layouts it visits, so keep it to small stacks
+    pub fn moves_to(&self, target: &Crane, model: Model) -> Option<String> {
+        let labels =
+            |stacks: &[Vec<String>]| stacks.iter().flatten().sorted().cloned().collect_vec();
+        if self.stacks.len() != target.stacks.len()
+            || labels(&self.stacks) != labels(&target.stacks)
+        {
+            return None;
+        }
+
+        let mut parents: HashMap<Layout, Option<(Layout, Operation)>> =
+            HashMap::from([(self.stacks.clone(), None)]);
+        let mut queue = VecDeque::from([self.stacks.clone()]);
+
+        while let Some(stacks) = queue.pop_front() {
+            if stacks == target.stacks {
+                let mut operations = vec![];
+                let mut current = &stacks;
+                while let Some(Some((previous, operation))) = parents.get(current) {
+                    operations.push(operation.to_string());
+                    current = previous;
+                }
+                return Some(operations.iter().rev().join("\n"));
+            }
+
+            for (source, target) in (1..=stacks.len()).tuple_combinations() {
+                for (source, target) in [(source, target), (target, source)] {
+                    for quantity in 1..=stacks[source - 1].len() {
+                        let operation = Operation {
+                            line: 0,
+                            quantity,
+                            source,
+                            target,
+                        };
+                        let mut next = stacks.clone();
+                        apply(&mut next, &operation, model);
+                        if !parents.contains_key(&next) {
+                            parents.insert(next.clone(), Some((stacks.clone(), operation)));
+                            queue.push_back(next);
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+fn apply(stacks: &mut [Vec<String>], operation: &Operation, model: Model) {
+    let source = &mut stacks[operation.source - 1];
+    let moved = source
+        .drain(source.len() - operation.quantity..)
+        .collect_vec();
+
+    // The top chunk is lifted first, and each chunk keeps its order
+    let target = &mut stacks[operation.target - 1];
+    for chunk in moved.rchunks(model.lift(operation.quantity)) {
+        target.extend_from_slice(chunk);
+    }
+}
+
+impl fmt::Display for Crane {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self
+            .stacks
+            .iter()
+            .flatten()
+            .map(|label| label.chars().count())
+            .max()
+            .unwrap_or(1);
+        let height = self.stacks.iter().map(Vec::len).max().unwrap_or(0);
+
+        for row in (0..height).rev() {
+            let line = self
+                .stacks
+                .iter()
+                .map(|stack| match stack.get(row) {
+                    Some(label) => format!("[{:^width$}]", label),
+                    None => " ".repeat(width + 2),
+                })
+                .join(" ");
+            writeln!(f, "{}", line)?;
+        }
+
+        let footer = (1..=self.stacks.len())
+            .map(|n| format!("{:^w$}", n, w = width + 2))
+            .join(" ");
+        write!(f, "{}", footer)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub content: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: cannot parse {:?}", self.line, self.content)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MoveError {
+    NoSuchStack(usize),
+    NotEnoughCrates {
+        stack: usize,
+        available: usize,
+        requested: usize,
+    },
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveError::NoSuchStack(stack) => write!(f, "there is no stack {}", stack),
+            MoveError::NotEnoughCrates {
+                stack,
+                available,
+                requested,
+            } => write!(
+                f,
+                "stack {} holds {} crates but {} were requested",
+                stack, available, requested
+            ),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct InvalidMove {
+    pub line: usize,
+    pub error: MoveError,
+}
+
+impl fmt::Display for InvalidMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Validation {
+    pub errors: Vec<InvalidMove>,
+    pub max_heights: Vec<usize>,
+    pub layout: Crane,
 }
 
 #[cfg(test)]
 mod tests {
     use std::collections::VecDeque;
 
-    use super::{Crane, Operation};
+    use super::{Crane, InvalidMove, Model, MoveError, Operation, ParseError};
+
+    fn stack(labels: &str) -> Vec<String> {
+        labels.chars().map(String::from).collect()
+    }
 
     #[test]
     fn it_should_parse_the_input() {
@@ -112,24 +407,28 @@ mod tests {
             .replace('\t', "");
 
         let expected = Crane {
-            stacks: vec![String::from("NZ"), String::from("DCM"), String::from("P")],
+            stacks: vec![stack("ZN"), stack("MCD"), stack("P")],
             operations: VecDeque::from_iter([
                 Operation {
+                    line: 6,
                     quantity: 1,
                     target: 1,
                     source: 2,
                 },
                 Operation {
+                    line: 7,
                     quantity: 3,
                     target: 3,
                     source: 1,
                 },
                 Operation {
+                    line: 8,
                     quantity: 2,
                     target: 1,
                     source: 2,
                 },
                 Operation {
+                    line: 9,
                     quantity: 1,
                     target: 2,
                     source: 1,
@@ -137,30 +436,34 @@ mod tests {
             ]),
         };
         let actual = Crane::from_str(&input);
-        assert_eq!(expected, actual);
+        assert_eq!(Ok(expected), actual);
     }
 
     #[test]
     fn it_should_perform_an_operation() {
         let mut crane = Crane {
-            stacks: vec![String::from("NZ"), String::from("DCM"), String::from("P")],
+            stacks: vec![stack("ZN"), stack("MCD"), stack("P")],
             operations: VecDeque::from_iter([
                 Operation {
+                    line: 6,
                     quantity: 1,
                     target: 1,
                     source: 2,
                 },
                 Operation {
+                    line: 7,
                     quantity: 3,
                     target: 3,
                     source: 1,
                 },
                 Operation {
+                    line: 8,
                     quantity: 2,
                     target: 1,
                     source: 2,
                 },
                 Operation {
+                    line: 9,
                     quantity: 1,
                     target: 2,
                     source: 1,
@@ -168,19 +471,22 @@ mod tests {
             ]),
         };
         let expected = Crane {
-            stacks: vec![String::from("DNZ"), String::from("CM"), String::from("P")],
+            stacks: vec![stack("ZND"), stack("MC"), stack("P")],
             operations: VecDeque::from_iter([
                 Operation {
+                    line: 7,
                     quantity: 3,
                     target: 3,
                     source: 1,
                 },
                 Operation {
+                    line: 8,
                     quantity: 2,
                     target: 1,
                     source: 2,
                 },
                 Operation {
+                    line: 9,
                     quantity: 1,
                     target: 2,
                     source: 1,
@@ -194,24 +500,28 @@ mod tests {
     #[test]
     fn it_should_perform_all_operations() {
         let mut crane = Crane {
-            stacks: vec![String::from("NZ"), String::from("DCM"), String::from("P")],
+            stacks: vec![stack("ZN"), stack("MCD"), stack("P")],
             operations: VecDeque::from_iter([
                 Operation {
+                    line: 6,
                     quantity: 1,
                     target: 1,
                     source: 2,
                 },
                 Operation {
+                    line: 7,
                     quantity: 3,
                     target: 3,
                     source: 1,
                 },
                 Operation {
+                    line: 8,
                     quantity: 2,
                     target: 1,
                     source: 2,
                 },
                 Operation {
+                    line: 9,
                     quantity: 1,
                     target: 2,
                     source: 1,
@@ -219,11 +529,222 @@ mod tests {
             ]),
         };
         let expected = Crane {
-            stacks: vec![String::from("C"), String::from("M"), String::from("ZNDP")],
+            stacks: vec![stack("C"), stack("M"), stack("PDNZ")],
             operations: VecDeque::new(),
         };
         let output = crane.operate_all();
         assert_eq!(expected, crane);
         assert_eq!("CMZ", output);
     }
+
+    fn sample() -> Crane {
+        Crane {
+            stacks: vec![stack("ZN"), stack("MCD"), stack("P")],
+            operations: VecDeque::from_iter([
+                Operation {
+                    line: 6,
+                    quantity: 1,
+                    target: 1,
+                    source: 2,
+                },
+                Operation {
+                    line: 7,
+                    quantity: 3,
+                    target: 3,
+                    source: 1,
+                },
+                Operation {
+                    line: 8,
+                    quantity: 2,
+                    target: 1,
+                    source: 2,
+                },
+                Operation {
+                    line: 9,
+                    quantity: 1,
+                    target: 2,
+                    source: 1,
+                },
+            ]),
+        }
+    }
+
+    #[test]
+    fn it_should_operate_each_crane_model() {
+        assert_eq!("CMZ", sample().operate_all_with(Model::CrateMover9000));
+        assert_eq!("MCD", sample().operate_all_with(Model::CrateMover9001));
+        assert_eq!(
+            "CMZ",
+            sample().operate_all_with(Model::capacity(1).unwrap())
+        );
+        assert_eq!(
+            "MCD",
+            sample().operate_all_with(Model::capacity(3).unwrap())
+        );
+
+        let mut crane = sample();
+        crane.operate_all_with(Model::capacity(2).unwrap());
+        assert_eq!(None, Model::capacity(0));
+        assert_eq!(stack("PNDZ"), crane.stacks[2]);
+    }
+
+    #[test]
+    fn it_should_parse_ragged_drawings_with_long_labels() {
+        let input = "    [D]\n[N] [CX]\n[Z] [M] [P]\n 1   2   3\n\nmove 1 from 2 to 1\n";
+        let crane = Crane::from_str(input).unwrap();
+
+        assert_eq!(
+            vec![
+                stack("ZN"),
+                vec![String::from("M"), String::from("CX"), String::from("D")],
+                stack("P")
+            ],
+            crane.stacks
+        );
+        assert_eq!(1, crane.operations.len());
+
+        let wide = "[AB]      [E]\n[CD] [XY] [F]\n  1    2   3\n";
+        assert_eq!(
+            vec![
+                vec![String::from("CD"), String::from("AB")],
+                vec![String::from("XY")],
+                stack("FE")
+            ],
+            Crane::from_str(wide).unwrap().stacks
+        );
+    }
+
+    #[test]
+    fn it_should_render_the_drawing() {
+        let drawing = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 ";
+        let mut crane = Crane::from_str(drawing).unwrap();
+        assert_eq!(drawing, crane.to_string());
+
+        crane.stacks[0].push(String::from("XY"));
+        let round_trip = Crane::from_str(&crane.to_string()).unwrap();
+        assert_eq!(crane.stacks, round_trip.stacks);
+    }
+
+    #[test]
+    fn it_should_report_each_step() {
+        let mut drawings = vec![];
+        let output = sample().operate_each(Model::CrateMover9000, |crane| {
+            drawings.push(crane.to_string())
+        });
+
+        assert_eq!("CMZ", output);
+        assert_eq!(4, drawings.len());
+        assert_eq!(
+            "[D]        \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 ",
+            drawings[0]
+        );
+    }
+
+    #[test]
+    fn it_should_validate_without_operating() {
+        let input = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 \n\n\
+            move 1 from 2 to 1\nmove 5 from 1 to 3\nmove 1 from 4 to 1\nmove 3 from 1 to 3\n";
+        let crane = Crane::from_str(input).unwrap();
+        let validation = crane.validate();
+
+        assert_eq!(
+            vec![
+                InvalidMove {
+                    line: 7,
+                    error: MoveError::NotEnoughCrates {
+                        stack: 1,
+                        available: 3,
+                        requested: 5,
+                    },
+                },
+                InvalidMove {
+                    line: 8,
+                    error: MoveError::NoSuchStack(4),
+                },
+            ],
+            validation.errors
+        );
+        assert_eq!(vec![3, 3, 4], validation.max_heights);
+        assert_eq!(
+            vec![vec![], stack("MC"), stack("PDNZ")],
+            validation.layout.stacks
+        );
+        assert_eq!(stack("ZN"), crane.stacks[0]);
+    }
+
+    #[test]
+    fn it_should_find_the_shortest_moves_between_layouts() {
+        let start = sample();
+        let mut target = sample();
+        target.operate_all();
+
+        let moves = start.moves_to(&target, Model::CrateMover9000).unwrap();
+        assert_eq!(3, moves.lines().count());
+
+        let drawing = format!("{}\n\n{}", start, moves);
+        let mut replay = Crane::from_str(&drawing).unwrap();
+        replay.operate_all();
+        assert_eq!(target.stacks, replay.stacks);
+
+        let moves = start.moves_to(&target, Model::CrateMover9001).unwrap();
+        assert_eq!(4, moves.lines().count());
+        let mut replay = Crane::from_str(&format!("{}\n\n{}", start, moves)).unwrap();
+        replay.operate_all_with(Model::CrateMover9001);
+        assert_eq!(target.stacks, replay.stacks);
+
+        assert_eq!(
+            Some(String::new()),
+            start.moves_to(&sample(), Model::CrateMover9000)
+        );
+    }
+
+    #[test]
+    fn it_should_not_plan_between_different_crates() {
+        let start = Crane::from_str("[A] [B]\n 1   2 ").unwrap();
+        let target = Crane::from_str("[A] [C]\n 1   2 ").unwrap();
+        assert_eq!(None, start.moves_to(&target, Model::CrateMover9000));
+
+        let target = Crane::from_str("[B]    \n[A]    \n 1   2 ").unwrap();
+        assert_eq!(
+            Some(String::from("move 1 from 2 to 1")),
+            start.moves_to(&target, Model::CrateMover9000)
+        );
+    }
+
+    #[test]
+    fn it_should_report_malformed_operations_with_their_line() {
+        let input = "[A] [B]\n 1   2 \n\nmove 1 from 1 to 2\nmove x from 1 to 1\n";
+
+        assert_eq!(
+            Err(ParseError {
+                line: 5,
+                content: String::from("move x from 1 to 1"),
+            }),
+            Crane::from_str(input)
+        );
+    }
+
+    #[test]
+    fn it_should_handle_missing_drawings_and_footers() {
+        let empty = Crane {
+            stacks: vec![],
+            operations: VecDeque::new(),
+        };
+        assert_eq!(
+            Ok(empty.stacks.clone()),
+            Crane::from_str(&empty.to_string()).map(|c| c.stacks)
+        );
+
+        let crane = Crane::from_str("\nmove 1 from 1 to 2").unwrap();
+        assert!(crane.stacks.is_empty());
+        assert_eq!(1, crane.validate().errors.len());
+
+        assert_eq!(
+            Err(ParseError {
+                line: 2,
+                content: String::from("[C] [D]"),
+            }),
+            Crane::from_str("[A] [B]\n[C] [D]\n\nmove 1 from 1 to 2")
+        );
+    }
 }
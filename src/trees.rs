@@ -1,5 +1,7 @@
+use std::{fmt, str::FromStr};
+
 use grid::{grid, Grid};
-use itertools::Itertools;
+use itertools::iproduct;
 
 #[derive(Debug, Default, PartialEq, Eq)]
 pub struct LineOfSight<T> {
@@ -23,41 +25,216 @@ impl Normalize for isize {
     }
 }
 
+// (row step, column step)
+pub type Direction = (isize, isize);
+
+pub const NORTH: Direction = (-1, 0);
+pub const SOUTH: Direction = (1, 0);
+pub const EAST: Direction = (0, 1);
+pub const WEST: Direction = (0, -1);
+pub const NORTH_EAST: Direction = (-1, 1);
+pub const NORTH_WEST: Direction = (-1, -1);
+pub const SOUTH_EAST: Direction = (1, 1);
+pub const SOUTH_WEST: Direction = (1, -1);
+
+pub const CARDINALS: [Direction; 4] = [NORTH, SOUTH, EAST, WEST];
+pub const DIAGONALS: [Direction; 4] = [NORTH_EAST, NORTH_WEST, SOUTH_EAST, SOUTH_WEST];
+
+// (layer step, row step, column step)
+pub type Direction3 = (isize, isize, isize);
+
+pub const ABOVE: Direction3 = (1, 0, 0);
+pub const BELOW: Direction3 = (-1, 0, 0);
+
+pub const AXES: [Direction3; 6] = [
+    ABOVE,
+    BELOW,
+    (0, NORTH.0, NORTH.1),
+    (0, SOUTH.0, SOUTH.1),
+    (0, EAST.0, EAST.1),
+    (0, WEST.0, WEST.1),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scoring {
+    Product,
+    Sum,
+    Min,
+}
+
+impl Scoring {
+    pub fn combine(&self, scores: impl Iterator<Item = usize>) -> usize {
+        match self {
+            Scoring::Product => scores.product(),
+            Scoring::Sum => scores.sum(),
+            Scoring::Min => scores.min().unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sight<D = Direction> {
+    directions: Vec<D>,
+    scoring: Scoring,
+}
+
+impl Default for Sight {
+    fn default() -> Self {
+        Sight::new(CARDINALS.to_vec(), Scoring::Product)
+    }
+}
+
+impl Default for Sight<Direction3> {
+    fn default() -> Self {
+        Sight::new(AXES.to_vec(), Scoring::Product)
+    }
+}
+
+impl<D: Default + PartialEq> Sight<D> {
+    pub fn new(directions: Vec<D>, scoring: Scoring) -> Sight<D> {
+        assert!(
+            !directions.contains(&D::default()),
+            "A direction must move at least one step"
+        );
+        Sight {
+            directions,
+            scoring,
+        }
+    }
+}
+
+impl<D> Sight<D> {
+    pub fn directions(&self) -> &[D] {
+        &self.directions
+    }
+
+    pub fn scoring(&self) -> Scoring {
+        self.scoring
+    }
+}
+
+impl Sight {
+    pub fn eight_way(scoring: Scoring) -> Sight {
+        Sight::new([CARDINALS, DIAGONALS].concat(), scoring)
+    }
+}
+
+impl Sight<Direction3> {
+    pub fn twenty_six_way(scoring: Scoring) -> Sight<Direction3> {
+        let directions = iproduct!(-1..=1, -1..=1, -1..=1)
+            .filter(|direction| *direction != (0, 0, 0))
+            .collect();
+        Sight::new(directions, scoring)
+    }
+}
+
 pub trait HasLineOfSight<T> {
+    fn line_of_sight(&self, u_row: usize, u_col: usize, direction: Direction) -> Vec<T>;
     fn lines_of_sight(&self, u_row: usize, u_col: usize) -> LineOfSight<T>;
 }
 
-impl HasLineOfSight<i32> for Grid<i32> {
-    fn lines_of_sight(&self, u_row: usize, u_col: usize) -> LineOfSight<i32> {
-        let col = self.iter_col(u_col);
-        let row = self.iter_row(u_row);
+impl<T: Ord + Copy> HasLineOfSight<T> for Grid<T> {
+    fn line_of_sight(&self, u_row: usize, u_col: usize, direction: Direction) -> Vec<T> {
+        let (d_row, d_col) = direction;
+        let mut line = vec![];
+        let mut row = u_row;
+        let mut col = u_col;
+        while let (Some(next_row), Some(next_col)) =
+            (row.checked_add_signed(d_row), col.checked_add_signed(d_col))
+        {
+            match self.get(next_row, next_col) {
+                Some(value) => line.push(*value),
+                None => break,
+            }
+            row = next_row;
+            col = next_col;
+        }
+        line
+    }
 
+    fn lines_of_sight(&self, u_row: usize, u_col: usize) -> LineOfSight<T> {
         LineOfSight {
-            north: col.clone().collect_vec()[..u_row]
-                .iter()
-                .map(|i| **i)
-                .rev()
-                .collect_vec(),
-            south: col.clone().collect_vec()[u_row + 1..]
-                .iter()
-                .map(|i| **i)
-                .collect_vec(),
-            east: row.clone().collect_vec()[u_col + 1..]
-                .iter()
-                .map(|i| **i)
-                .collect_vec(),
-            west: row.clone().collect_vec()[..u_col]
-                .iter()
-                .map(|i| **i)
-                .rev()
-                .collect_vec(),
+            north: self.line_of_sight(u_row, u_col, NORTH),
+            south: self.line_of_sight(u_row, u_col, SOUTH),
+            east: self.line_of_sight(u_row, u_col, EAST),
+            west: self.line_of_sight(u_row, u_col, WEST),
         }
     }
 }
 
+// Visibility and scenic scores for any heightmap, in whatever dimensions D steps through
+pub trait Sightlines<T: Ord + Copy, D: Copy> {
+    type Cell: Copy;
+
+    fn cells(&self) -> Vec<Self::Cell>;
+    fn height(&self, cell: Self::Cell) -> Option<T>;
+    fn sight_line(&self, cell: Self::Cell, direction: D) -> Vec<T>;
+
+    fn cell_visible(&self, cell: Self::Cell, sight: &Sight<D>) -> bool {
+        let Some(value) = self.height(cell) else {
+            return false;
+        };
+        sight
+            .directions()
+            .iter()
+            .any(|direction| self.sight_line(cell, *direction).has_visible(value))
+    }
+
+    fn cells_visible(&self, sight: &Sight<D>) -> usize {
+        self.cells()
+            .into_iter()
+            .filter(|cell| self.cell_visible(*cell, sight))
+            .count()
+    }
+
+    fn cell_score(&self, cell: Self::Cell, sight: &Sight<D>) -> usize {
+        let Some(item) = self.height(cell) else {
+            return 0;
+        };
+        sight.scoring().combine(
+            sight
+                .directions()
+                .iter()
+                .map(|direction| self.sight_line(cell, *direction).scene_score(item)),
+        )
+    }
+
+    fn best_score(&self, sight: &Sight<D>) -> usize {
+        self.cells()
+            .into_iter()
+            .map(|cell| self.cell_score(cell, sight))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl<T: Ord + Copy> Sightlines<T, Direction> for Grid<T> {
+    type Cell = (usize, usize);
+
+    fn cells(&self) -> Vec<(usize, usize)> {
+        iproduct!(0..self.rows(), 0..self.cols()).collect()
+    }
+
+    fn height(&self, (row, col): (usize, usize)) -> Option<T> {
+        self.get(row, col).copied()
+    }
+
+    fn sight_line(&self, (row, col): (usize, usize), direction: Direction) -> Vec<T> {
+        self.line_of_sight(row, col, direction)
+    }
+}
+
 pub trait Visible<A, T: HasLineOfSight<A> = Self> {
-    fn visible(&self, row: usize, col: usize) -> bool;
-    fn count_visible(&self) -> usize;
+    fn visible_with(&self, row: usize, col: usize, sight: &Sight) -> bool;
+    fn count_visible_with(&self, sight: &Sight) -> usize;
+
+    fn visible(&self, row: usize, col: usize) -> bool {
+        self.visible_with(row, col, &Sight::default())
+    }
+
+    fn count_visible(&self) -> usize {
+        self.count_visible_with(&Sight::default())
+    }
 }
 
 trait HasVisible<A> {
@@ -65,12 +242,12 @@ trait HasVisible<A> {
     fn scene_score(&self, cmp: A) -> usize;
 }
 
-impl HasVisible<i32> for Vec<i32> {
-    fn has_visible(&self, cmp: i32) -> bool {
+impl<T: Ord + Copy> HasVisible<T> for Vec<T> {
+    fn has_visible(&self, cmp: T) -> bool {
         self.iter().copied().filter(|v| *v >= cmp).count() == 0
     }
 
-    fn scene_score(&self, cmp: i32) -> usize {
+    fn scene_score(&self, cmp: T) -> usize {
         let mut score = 0;
         for entry in self.iter() {
             score += 1;
@@ -83,64 +260,130 @@ impl HasVisible<i32> for Vec<i32> {
     }
 }
 
-impl Visible<i32> for Grid<i32> {
-    fn visible(&self, row: usize, col: usize) -> bool {
-        if row == 0 || col == 0 || row == self.rows() - 1 || col == self.cols() - 1 {
-            true
-        } else {
-            let value = self.get(row, col).unwrap();
-            let los = self.lines_of_sight(row, col);
-
-            los.north.has_visible(*value)
-                || los.south.has_visible(*value)
-                || los.east.has_visible(*value)
-                || los.west.has_visible(*value)
-        }
+impl<T: Ord + Copy> Visible<T> for Grid<T> {
+    fn visible_with(&self, row: usize, col: usize, sight: &Sight) -> bool {
+        self.cell_visible((row, col), sight)
     }
 
-    fn count_visible(&self) -> usize {
-        let mut count = 0;
-        for row in 0..self.rows() {
-            for col in 0..self.cols() {
-                if self.visible(row, col) {
-                    count += 1;
-                }
-            }
-        }
-        count
+    fn count_visible_with(&self, sight: &Sight) -> usize {
+        self.cells_visible(sight)
     }
 }
 
 pub trait Scenic {
-    fn scene_score(&self, row: usize, col: usize) -> usize;
-    fn highest_score(&self) -> usize;
-}
+    fn scene_score_with(&self, row: usize, col: usize, sight: &Sight) -> usize;
+    fn highest_score_with(&self, sight: &Sight) -> usize;
 
-impl Scenic for Grid<i32> {
     fn scene_score(&self, row: usize, col: usize) -> usize {
-        if row == 0 || col == 0 || row == self.rows() - 1 || col == self.cols() - 1 {
-            0
-        } else {
-            let item = *self.get(row, col).unwrap();
-            let los = self.lines_of_sight(row, col);
-            los.north.scene_score(item)
-                * los.south.scene_score(item)
-                * los.east.scene_score(item)
-                * los.west.scene_score(item)
-        }
+        self.scene_score_with(row, col, &Sight::default())
     }
 
     fn highest_score(&self) -> usize {
-        let mut high_score = 0;
-        for row in 0..self.rows() {
-            for col in 0..self.rows() {
-                let score = self.scene_score(row, col);
-                if score > high_score {
-                    high_score = score;
-                }
+        self.highest_score_with(&Sight::default())
+    }
+}
+
+impl<T: Ord + Copy> Scenic for Grid<T> {
+    fn scene_score_with(&self, row: usize, col: usize, sight: &Sight) -> usize {
+        self.cell_score((row, col), sight)
+    }
+
+    fn highest_score_with(&self, sight: &Sight) -> usize {
+        self.best_score(sight)
+    }
+}
+
+#[derive(Debug)]
+pub struct Volume<T> {
+    layers: Vec<Grid<T>>,
+}
+
+impl<T: Ord + Copy> Volume<T> {
+    pub fn new(layers: Vec<Grid<T>>) -> Volume<T> {
+        if let Some(first) = layers.first() {
+            assert!(
+                layers.iter().all(|layer| layer.size() == first.size()),
+                "All layers must be the same size"
+            );
+        }
+        Volume { layers }
+    }
+
+    pub fn layers(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn get(&self, layer: usize, row: usize, col: usize) -> Option<&T> {
+        self.layers.get(layer).and_then(|grid| grid.get(row, col))
+    }
+
+    pub fn line_of_sight(
+        &self,
+        layer: usize,
+        row: usize,
+        col: usize,
+        direction: Direction3,
+    ) -> Vec<T> {
+        let (d_layer, d_row, d_col) = direction;
+        if d_layer == 0 {
+            return self
+                .layers
+                .get(layer)
+                .map(|grid| grid.line_of_sight(row, col, (d_row, d_col)))
+                .unwrap_or_default();
+        }
+
+        let mut line = vec![];
+        let mut position = (layer, row, col);
+        while let (Some(next_layer), Some(next_row), Some(next_col)) = (
+            position.0.checked_add_signed(d_layer),
+            position.1.checked_add_signed(d_row),
+            position.2.checked_add_signed(d_col),
+        ) {
+            match self.get(next_layer, next_row, next_col) {
+                Some(value) => line.push(*value),
+                None => break,
             }
+            position = (next_layer, next_row, next_col);
         }
-        high_score
+        line
+    }
+}
+
+impl<T: Ord + Copy> Sightlines<T, Direction3> for Volume<T> {
+    type Cell = (usize, usize, usize);
+
+    fn cells(&self) -> Vec<(usize, usize, usize)> {
+        self.layers
+            .iter()
+            .enumerate()
+            .flat_map(|(layer, grid)| {
+                iproduct!(0..grid.rows(), 0..grid.cols()).map(move |(row, col)| (layer, row, col))
+            })
+            .collect()
+    }
+
+    fn height(&self, (layer, row, col): (usize, usize, usize)) -> Option<T> {
+        self.get(layer, row, col).copied()
+    }
+
+    fn sight_line(
+        &self,
+        (layer, row, col): (usize, usize, usize),
+        direction: Direction3,
+    ) -> Vec<T> {
+        self.line_of_sight(layer, row, col, direction)
+    }
+}
+
+impl Volume<i32> {
+    pub fn construct(inputs: Vec<&str>) -> Volume<i32> {
+        let layers = inputs
+            .split(|line| line.is_empty())
+            .filter(|layer| !layer.is_empty())
+            .map(|layer| construct(layer.to_vec()))
+            .collect();
+        Volume::new(layers)
     }
 }
 
@@ -156,12 +399,76 @@ pub fn construct(inputs: Vec<&str>) -> Grid<i32> {
     grid
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub enum ConstructError<E> {
+    InvalidHeight {
+        line: usize,
+        error: E,
+    },
+    RaggedRow {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl<E: fmt::Display> fmt::Display for ConstructError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConstructError::InvalidHeight { line, error } => {
+                write!(f, "line {}: invalid height: {}", line, error)
+            }
+            ConstructError::RaggedRow {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {}: expected {} heights, found {}",
+                line, expected, found
+            ),
+        }
+    }
+}
+
+pub fn construct_separated<T: FromStr>(
+    inputs: Vec<&str>,
+) -> Result<Grid<T>, ConstructError<T::Err>> {
+    let mut grid: Grid<T> = grid![];
+    for (idx, line) in inputs.iter().enumerate() {
+        let row = line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|height| !height.is_empty())
+            .map(|height| height.parse::<T>())
+            .collect::<Result<Vec<T>, T::Err>>()
+            .map_err(|error| ConstructError::InvalidHeight {
+                line: idx + 1,
+                error,
+            })?;
+        if row.is_empty() {
+            continue;
+        }
+        if !grid.is_empty() && row.len() != grid.cols() {
+            return Err(ConstructError::RaggedRow {
+                line: idx + 1,
+                expected: grid.cols(),
+                found: row.len(),
+            });
+        }
+        grid.push_row(row);
+    }
+    Ok(grid)
+}
+
 #[cfg(test)]
 mod tests {
     use grid::grid;
     use itertools::Itertools;
 
-    use super::{construct, HasLineOfSight, LineOfSight, Scenic, Visible};
+    use super::{
+        construct, construct_separated, ConstructError, HasLineOfSight, LineOfSight, Scenic,
+        Scoring, Sight, Sightlines, Visible, Volume, NORTH_WEST, SOUTH_EAST, SOUTH_WEST,
+    };
 
     #[test]
     fn it_constructs_a_grid() {
@@ -308,4 +615,145 @@ mod tests {
         ];
         assert_eq!(8, grid.highest_score());
     }
+
+    #[test]
+    fn it_gets_a_diagonal_line_of_sight() {
+        let grid = grid![
+            [3,0,3,7,3]
+            [2,5,5,1,2]
+            [6,5,3,3,2]
+            [3,3,5,4,9]
+            [3,5,3,9,0]
+        ];
+        assert_eq!(vec![5, 3], grid.line_of_sight(2, 2, NORTH_WEST));
+        assert_eq!(vec![4, 0], grid.line_of_sight(2, 2, SOUTH_EAST));
+        assert_eq!(vec![3, 3], grid.line_of_sight(2, 2, SOUTH_WEST));
+        assert_eq!(Vec::<i32>::new(), grid.line_of_sight(0, 0, NORTH_WEST));
+    }
+
+    #[test]
+    fn it_counts_visible_trees_with_eight_way_sight() {
+        let grid = grid![
+            [3,0,3,7,3]
+            [2,5,5,1,2]
+            [6,5,3,3,2]
+            [3,3,5,4,9]
+            [3,5,3,9,0]
+        ];
+        let sight = Sight::eight_way(Scoring::Product);
+
+        assert!(!grid.visible_with(3, 1, &sight));
+        assert_eq!(22, grid.count_visible_with(&sight));
+    }
+
+    #[test]
+    fn it_combines_scores_with_the_configured_scoring() {
+        let grid = grid![
+            [3,0,3,7,3]
+            [2,5,5,1,2]
+            [6,5,3,3,2]
+            [3,3,5,4,9]
+            [3,5,3,9,0]
+        ];
+        let sum = Sight::new(super::CARDINALS.to_vec(), Scoring::Sum);
+        let min = Sight::new(super::CARDINALS.to_vec(), Scoring::Min);
+
+        assert_eq!(7, grid.scene_score_with(3, 2, &sum));
+        assert_eq!(1, grid.scene_score_with(3, 2, &min));
+        assert_eq!(8, grid.scene_score_with(3, 2, &Sight::default()));
+    }
+
+    #[test]
+    fn it_constructs_a_grid_from_separated_heights() {
+        let input = vec!["120, 35, 1200", "8 4000 16", "", "7,7,  9"];
+
+        let expected = grid![
+            [120,35,1200]
+            [8,4000,16]
+            [7,7,9]
+        ];
+        let result = construct_separated::<u32>(input).unwrap();
+        assert_eq!(expected, result);
+        assert!(construct_separated::<u32>(vec!["12 x4"]).is_err());
+    }
+
+    #[test]
+    fn it_rejects_ragged_rows() {
+        assert_eq!(
+            Err(ConstructError::RaggedRow {
+                line: 3,
+                expected: 3,
+                found: 2,
+            }),
+            construct_separated::<u32>(vec!["1 2 3", "", "4 5"])
+        );
+    }
+
+    #[test]
+    fn it_works_with_any_ordered_height() {
+        let grid = grid![
+            ['c','a','c']
+            ['b','e','b']
+            ['f','e','c']
+        ];
+        assert!(grid.visible(1, 1));
+        assert_eq!(9, grid.count_visible());
+        assert_eq!(1, grid.scene_score(1, 1));
+    }
+
+    #[test]
+    fn it_constructs_a_volume_from_layers() {
+        let input = vec![
+            "111", "111", "111", "", "121", "232", "121", "", "111", "111", "111",
+        ];
+        let volume = Volume::construct(input);
+
+        assert_eq!(3, volume.layers());
+        assert_eq!(Some(&3), volume.get(1, 1, 1));
+        assert_eq!(None, volume.get(3, 0, 0));
+    }
+
+    #[test]
+    fn it_gets_a_line_of_sight_through_the_layers() {
+        let volume = Volume::construct(vec!["123", "456", "", "789", "012", "", "345", "678"]);
+
+        assert_eq!(vec![0, 6], volume.line_of_sight(0, 1, 0, super::ABOVE));
+        assert_eq!(vec![1, 5], volume.line_of_sight(2, 1, 1, super::BELOW));
+        assert_eq!(vec![1], volume.line_of_sight(1, 0, 1, super::AXES[3]));
+        assert_eq!(vec![1], volume.line_of_sight(0, 0, 0, (1, 1, 1)));
+
+        assert!(volume.line_of_sight(3, 0, 0, super::AXES[3]).is_empty());
+        assert!(volume.line_of_sight(3, 0, 0, super::ABOVE).is_empty());
+    }
+
+    #[test]
+    fn it_tests_volume_cells_for_visibility() {
+        let volume = Volume::construct(vec![
+            "999", "999", "999", "", "999", "959", "999", "", "999", "999", "999",
+        ]);
+        let sight = Sight::default();
+        assert!(!volume.cell_visible((1, 1, 1), &sight));
+        assert_eq!(26, volume.cells_visible(&sight));
+
+        let volume = Volume::construct(vec![
+            "999", "919", "999", "", "999", "959", "999", "", "999", "999", "999",
+        ]);
+        assert!(volume.cell_visible((1, 1, 1), &sight));
+        assert_eq!(27, volume.cells_visible(&sight));
+    }
+
+    #[test]
+    fn it_scores_volume_cells() {
+        let volume = Volume::construct(vec![
+            "000", "010", "000", "", "000", "050", "000", "", "000", "090", "000",
+        ]);
+        let sight = Sight::default();
+        assert_eq!(1, volume.cell_score((1, 1, 1), &sight));
+        assert_eq!(0, volume.cell_score((0, 0, 0), &sight));
+        assert_eq!(1, volume.best_score(&sight));
+
+        let sum = Sight::twenty_six_way(Scoring::Sum);
+        assert_eq!(26, sum.directions().len());
+        assert_eq!(26, volume.cell_score((1, 1, 1), &sum));
+    }
 }